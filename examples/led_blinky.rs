@@ -5,7 +5,6 @@
 #![no_std]
 #![no_main]
 
-use core::cell::RefCell;
 use cortex_m::asm;
 use cortex_m_rt::{entry, exception, ExceptionFrame};
 use hal::{
@@ -16,7 +15,7 @@ use hal::{
     pac,
     prelude::*,
 };
-use non_preemptive_scheduler::{resources::UnShared, EventMask, Scheduler, Task};
+use non_preemptive_scheduler::{resources::Late, EventMask};
 use non_preemptive_scheduler_macros as scheduler;
 use rtt_target::{rprintln as log, rtt_init_print as log_init};
 use stm32f4xx_hal as hal;
@@ -24,33 +23,33 @@ use stm32f4xx_hal as hal;
 // Events
 const EVENT_TOGGLE_RED_LED: EventMask = 0x00000001;
 // Static and interior mutable entities
-static GREEN_LED: UnShared<RefCell<Option<PG13<Output<PushPull>>>>> =
-    UnShared::new(RefCell::new(None));
-static RED_LED: UnShared<RefCell<Option<PG14<Output<PushPull>>>>> =
-    UnShared::new(RefCell::new(None));
-
-// Create scheduler
-#[scheduler::new(task_count = 3, core_freq = 180_000_000)]
-struct NonPreemptiveScheduler;
+static GREEN_LED: Late<PG13<Output<PushPull>>> = Late::new();
+static RED_LED: Late<PG14<Output<PushPull>>> = Late::new();
+
+// Create scheduler along with its tasks
+scheduler::tasks! {
+    task_count = 3,
+    core_freq = 180_000_000,
+    tasks = [
+        // (Task name, Init runnable, Process runnable, Execution cycle, Execution offset)
+        ("green_led_blinky", None, Some(green_led_blinky), Some(1_000), Some(3)),
+        ("red_led_switcher", None, Some(red_led_switcher), Some(1_000), Some(5)),
+        ("red_led_blinky", Some(red_led_on), Some(red_led_blinky), None, None),
+    ],
+}
 
 // Functions which are bound to task runnables
 fn green_led_blinky(_: EventMask) {
-    if let Some(led_green) = GREEN_LED.borrow().borrow_mut().as_mut() {
-        led_green.toggle();
-    }
+    GREEN_LED.get().toggle();
 }
 
 fn red_led_on() {
-    if let Some(led_red) = RED_LED.borrow().borrow_mut().as_mut() {
-        led_red.set_high();
-    }
+    RED_LED.get().set_high();
 }
 
 fn red_led_blinky(event_mask: EventMask) {
     if event_mask & EVENT_TOGGLE_RED_LED != 0 {
-        if let Some(led_red) = RED_LED.borrow().borrow_mut().as_mut() {
-            led_red.toggle();
-        }
+        RED_LED.get().toggle();
     }
 }
 
@@ -72,12 +71,8 @@ fn bsp_init() {
 
     // Initialize LEDs
     let gpio_g = dp.GPIOG.split();
-    GREEN_LED
-        .borrow()
-        .replace(Some(gpio_g.pg13.into_push_pull_output()));
-    RED_LED
-        .borrow()
-        .replace(Some(gpio_g.pg14.into_push_pull_output()));
+    GREEN_LED.init(gpio_g.pg13.into_push_pull_output());
+    RED_LED.init(gpio_g.pg14.into_push_pull_output());
 }
 
 #[entry]
@@ -86,30 +81,8 @@ fn main() -> ! {
 
     bsp_init();
 
-    // Create and add tasks
-    scheduler::add_task!(
-        "green_led_blinky",     // Task name
-        None,                   // Init runnable
-        Some(green_led_blinky), // Process runnable
-        Some(1_000),            // Execution cycle
-        Some(3)                 // Execution offset
-    );
-
-    scheduler::add_task!(
-        "red_led_switcher",
-        None,
-        Some(red_led_switcher),
-        Some(1_000),
-        Some(5)
-    );
-
-    scheduler::add_task!(
-        "red_led_blinky",
-        Some(red_led_on),
-        Some(red_led_blinky),
-        None,
-        None
-    );
+    // Add declared tasks
+    add_tasks();
 
     // Register idle runnable (optional)
     scheduler::register_idle_runnable!(asm::nop);
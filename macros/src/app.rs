@@ -0,0 +1,593 @@
+//! Declarative application which derives the scheduler
+//! configuration from the annotated functions of a module
+
+use crate::{
+    resources::{self, Kind, Resource, Resources},
+    validate_schedule_table, validate_tasks, ScheduleTable, Task,
+};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse::*, parse_quote, punctuated::Punctuated, Attribute, Error, Expr, ExprLit, Ident, Item,
+    ItemFn, ItemMod, Lit, LitInt, LitStr, ReturnType, Token,
+};
+
+/// Comma separated list of `name = expression` pairs
+pub struct Args(Punctuated<Arg, Token![,]>);
+
+pub struct Arg {
+    name: Ident,
+    value: Expr,
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value: Expr = input.parse()?;
+        Ok(Arg { name, value })
+    }
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(Args(Punctuated::parse_terminated(input)?))
+    }
+}
+
+impl Args {
+    fn from_attribute(attr: &Attribute) -> Result<Self> {
+        if attr.tokens.is_empty() {
+            Ok(Args(Punctuated::new()))
+        } else {
+            attr.parse_args()
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Arg> {
+        self.0.iter()
+    }
+}
+
+struct AppTask {
+    task: Task,
+    output: ReturnType,
+    fallible: bool,
+    error_policy: Option<Expr>,
+    budget: Option<Expr>,
+    priority: Option<Expr>,
+    /// Interrupts masked on event, unmasked once the task has processed the event
+    unmask: Vec<(Ident, Expr)>,
+}
+
+impl AppTask {
+    fn parse<'a>(
+        function: &ItemFn,
+        attr: &Attribute,
+        resources: &'a Resources,
+    ) -> Result<(Self, Vec<(&'a Resource, Span)>)> {
+        let (mut init_runnable, mut execution_cycle, mut execution_offset, mut error_policy) =
+            (None, None, None, None);
+        let (mut budget, mut priority) = (None, None);
+        let mut granted = Vec::new();
+        for Arg { name, value } in Args::from_attribute(attr)?.iter() {
+            match name.to_string().as_str() {
+                "init" => init_runnable = Some(value.clone()),
+                "period" => execution_cycle = Some(value.clone()),
+                "offset" => execution_offset = Some(value.clone()),
+                "error_policy" => error_policy = Some(value.clone()),
+                "budget" => budget = Some(value.clone()),
+                "priority" => priority = Some(value.clone()),
+                "resources" => granted = resources.parse_list(value)?,
+                _ => return Err(Error::new(name.span(), "Unrecognized task argument")),
+            }
+        }
+
+        let fallible = matches!(function.sig.output, ReturnType::Type(..));
+        if error_policy.is_some() && !fallible {
+            return Err(Error::new_spanned(
+                &function.sig,
+                "`error_policy` requires a process runnable returning `Result<(), TaskError>`",
+            ));
+        }
+
+        let ident = &function.sig.ident;
+        let path = resources::path(ident);
+        let app_task = AppTask {
+            task: Task {
+                name: LitStr::new(&ident.to_string(), ident.span()),
+                init_runnable: optional(init_runnable),
+                process_runnable: parse_quote!(Some(#path)),
+                execution_cycle: optional(execution_cycle),
+                execution_offset: optional(execution_offset),
+            },
+            output: function.sig.output.clone(),
+            fallible,
+            error_policy,
+            budget,
+            priority,
+            unmask: Vec::new(),
+        };
+        Ok((app_task, granted))
+    }
+
+    /// Wraps the process runnable to unmask the interrupts routed to the task
+    /// once their event has been processed, the wrapper replaces the runnable
+    fn unmask_wrapper(&mut self, device: &Option<Expr>) -> Item {
+        let name = Ident::new(&self.task.name.value(), self.task.name.span());
+        let wrapper = format_ident!("__{}_unmask", name);
+        let path = resources::path(&name);
+        let output = &self.output;
+        let unmask = self.unmask.iter().map(|(binds, event)| {
+            quote! {
+                if event_mask & ::non_preemptive_scheduler::EventMask::from(#event) != 0 {
+                    unsafe { ::cortex_m::peripheral::NVIC::unmask(#device::Interrupt::#binds) };
+                }
+            }
+        });
+        let body = if self.fallible {
+            quote! {
+                let result = #path(event_mask);
+                #(#unmask)*
+                result
+            }
+        } else {
+            quote! {
+                #path(event_mask);
+                #(#unmask)*
+            }
+        };
+        self.task.process_runnable = parse_quote!(Some(#wrapper));
+        parse_quote! {
+            #[doc(hidden)]
+            fn #wrapper(event_mask: ::non_preemptive_scheduler::EventMask) #output {
+                #body
+            }
+        }
+    }
+
+    fn expand(&self) -> TokenStream {
+        let AppTask {
+            task:
+                Task {
+                    name,
+                    init_runnable,
+                    process_runnable,
+                    execution_cycle,
+                    execution_offset,
+                },
+            fallible,
+            error_policy,
+            budget,
+            priority,
+            ..
+        } = self;
+        let budget = budget.as_ref().map(|budget| quote!(.with_budget(#budget)));
+        let priority = priority
+            .as_ref()
+            .map(|priority| quote!(.with_priority(#priority)));
+        if *fallible {
+            let error_policy = error_policy
+                .clone()
+                .unwrap_or_else(|| parse_quote!(::non_preemptive_scheduler::ErrorPolicy::Ignore));
+            quote! {
+                scheduler.add_task(::non_preemptive_scheduler::Task::new_fallible(#name, #init_runnable, #process_runnable, #execution_cycle, #execution_offset, #error_policy)#priority #budget);
+            }
+        } else {
+            quote! {
+                scheduler.add_task(::non_preemptive_scheduler::Task::new(#name, #init_runnable, #process_runnable, #execution_cycle, #execution_offset)#priority #budget);
+            }
+        }
+    }
+}
+
+/// Interrupt handler, which can only access shared resources
+struct Interrupt {
+    binds: Ident,
+    handler: Ident,
+}
+
+impl Interrupt {
+    fn parse<'a>(
+        function: &ItemFn,
+        attr: &Attribute,
+        resources: &'a Resources,
+    ) -> Result<(Self, Vec<(&'a Resource, Span)>)> {
+        let (mut binds, mut granted) = (None, Vec::new());
+        for Arg { name, value } in Args::from_attribute(attr)?.iter() {
+            match name.to_string().as_str() {
+                "binds" => binds = Some(ident_of(value)?),
+                "resources" => granted = resources.parse_list(value)?,
+                _ => return Err(Error::new(name.span(), "Unrecognized interrupt argument")),
+            }
+        }
+        let binds = binds.ok_or_else(|| {
+            Error::new_spanned(attr, "`binds` argument is required, e.g. `binds = OTG_HS`")
+        })?;
+
+        check_shared(&granted, &binds)?;
+
+        let interrupt = Interrupt {
+            binds,
+            handler: function.sig.ident.clone(),
+        };
+        Ok((interrupt, granted))
+    }
+}
+
+/// Interrupt routed to a task event, the bound function acknowledges the interrupt
+struct InterruptEvent {
+    binds: Ident,
+    task: LitStr,
+    event: Expr,
+    mask: bool,
+    handler: Ident,
+}
+
+impl InterruptEvent {
+    fn parse<'a>(
+        function: &ItemFn,
+        attr: &Attribute,
+        resources: &'a Resources,
+    ) -> Result<(Self, Vec<(&'a Resource, Span)>)> {
+        let (binds, args) = attr.parse_args_with(|input: ParseStream| {
+            let binds: Ident = input.parse()?;
+            let args = if input.is_empty() {
+                Args(Punctuated::new())
+            } else {
+                input.parse::<Token![,]>()?;
+                input.parse()?
+            };
+            Ok((binds, args))
+        })?;
+        let (mut task, mut event, mut mask, mut granted) = (None, None, false, Vec::new());
+        for Arg { name, value } in args.iter() {
+            match name.to_string().as_str() {
+                "task" => task = Some(lit_str_of(value)?),
+                "event" => event = Some(value.clone()),
+                "mask" => mask = lit_bool_of(value)?,
+                "resources" => granted = resources.parse_list(value)?,
+                _ => {
+                    return Err(Error::new(
+                        name.span(),
+                        "Unrecognized interrupt event argument",
+                    ))
+                }
+            }
+        }
+        let task = task.ok_or_else(|| {
+            Error::new_spanned(
+                attr,
+                "`task` argument is required, e.g. `task = \"usb_process\"`",
+            )
+        })?;
+        let event = event.ok_or_else(|| {
+            Error::new_spanned(attr, "`event` argument is required, e.g. `event = USB_IRQ`")
+        })?;
+        check_shared(&granted, &binds)?;
+
+        let interrupt_event = InterruptEvent {
+            binds,
+            task,
+            event,
+            mask,
+            handler: function.sig.ident.clone(),
+        };
+        Ok((interrupt_event, granted))
+    }
+}
+
+fn check_shared(granted: &[(&Resource, Span)], binds: &Ident) -> Result<()> {
+    for (resource, span) in granted.iter() {
+        if resource.kind() != Kind::Shared {
+            return Err(Error::new(
+                *span,
+                format!(
+                    "Resource {} is not shared and cannot be accessed from interrupt {}, \
+                     declare it as `Shared` instead",
+                    resource.ident(),
+                    binds
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn lit_str_of(value: &Expr) -> Result<LitStr> {
+    match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => Ok(lit.clone()),
+        _ => Err(Error::new_spanned(value, "Expected a string literal")),
+    }
+}
+
+fn lit_bool_of(value: &Expr) -> Result<bool> {
+    match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Bool(lit),
+            ..
+        }) => Ok(lit.value),
+        _ => Err(Error::new_spanned(value, "Expected `true` or `false`")),
+    }
+}
+
+fn ident_of(value: &Expr) -> Result<Ident> {
+    match value {
+        Expr::Path(path) => path.path.get_ident().cloned(),
+        _ => None,
+    }
+    .ok_or_else(|| Error::new_spanned(value, "Expected an identifier"))
+}
+
+fn granted_resources<'a>(
+    attr: &Attribute,
+    resources: &'a Resources,
+) -> Result<Vec<(&'a Resource, Span)>> {
+    let mut granted = Vec::new();
+    for Arg { name, value } in Args::from_attribute(attr)?.iter() {
+        match name.to_string().as_str() {
+            "resources" => granted = resources.parse_list(value)?,
+            _ => return Err(Error::new(name.span(), "Unrecognized argument")),
+        }
+    }
+    Ok(granted)
+}
+
+fn optional(value: Option<Expr>) -> Expr {
+    match value {
+        Some(value) => parse_quote!(Some(#value)),
+        None => parse_quote!(None),
+    }
+}
+
+/// Takes the attribute named `name` out of the function, if any
+fn take_attribute(function: &mut ItemFn, name: &str) -> Option<Attribute> {
+    let position = function
+        .attrs
+        .iter()
+        .position(|attr| attr.path.is_ident(name))?;
+    Some(function.attrs.remove(position))
+}
+
+fn single(
+    current: &mut Option<Ident>,
+    function: &ItemFn,
+    attr: &Attribute,
+    name: &str,
+) -> Result<()> {
+    if current.is_some() {
+        return Err(Error::new_spanned(
+            attr,
+            format!("Only one `#[{}]` function is allowed", name),
+        ));
+    }
+    *current = Some(function.sig.ident.clone());
+    Ok(())
+}
+
+pub fn expand(args: Args, mut module: ItemMod) -> Result<TokenStream> {
+    let (mut core_freq, mut device, mut auto_offsets) = (None, None, false);
+    let (mut dispatch_policy, mut schedule_table) = (None, None);
+    for Arg { name, value } in args.iter() {
+        match name.to_string().as_str() {
+            "core_freq" => core_freq = Some(value.clone()),
+            "device" => device = Some(value.clone()),
+            "auto_offsets" => auto_offsets = lit_bool_of(value)?,
+            "dispatch_policy" => dispatch_policy = Some(value.clone()),
+            "schedule_table" => {
+                schedule_table =
+                    Some(ScheduleTable::parse_parenthesized.parse2(value.to_token_stream())?)
+            }
+            _ => return Err(Error::new(name.span(), "Unrecognized argument")),
+        }
+    }
+    let core_freq = core_freq
+        .ok_or_else(|| Error::new(Span::call_site(), "`core_freq` argument is required"))?;
+
+    let (_, items) = module
+        .content
+        .as_mut()
+        .ok_or_else(|| Error::new_spanned(&module.ident, "Application module must have a body"))?;
+
+    let resources = Resources::collect(items);
+    let (mut init, mut idle, mut tasks, mut interrupts) = (None, None, Vec::new(), Vec::new());
+    let (mut interrupt_events, mut grants) = (Vec::new(), Vec::new());
+    for item in items.iter_mut() {
+        if let Item::Fn(function) = item {
+            if let Some(attr) = take_attribute(function, "init") {
+                single(&mut init, function, &attr, "init")?;
+                // Init is executed before launching, all resources are granted to initialize them
+                grants.push(resources::grant(function, resources.all()));
+            } else if let Some(attr) = take_attribute(function, "idle") {
+                single(&mut idle, function, &attr, "idle")?;
+                let granted = granted_resources(&attr, &resources)?;
+                grants.push(resources::grant(
+                    function,
+                    granted.into_iter().map(|(resource, _)| resource),
+                ));
+            } else if let Some(attr) = take_attribute(function, "task") {
+                let (task, granted) = AppTask::parse(function, &attr, &resources)?;
+                grants.push(resources::grant(
+                    function,
+                    granted.into_iter().map(|(resource, _)| resource),
+                ));
+                tasks.push(task);
+            } else if let Some(attr) = take_attribute(function, "interrupt") {
+                let (interrupt, granted) = Interrupt::parse(function, &attr, &resources)?;
+                grants.push(resources::grant(
+                    function,
+                    granted.into_iter().map(|(resource, _)| resource),
+                ));
+                interrupts.push(interrupt);
+            } else if let Some(attr) = take_attribute(function, "interrupt_event") {
+                let (interrupt_event, granted) =
+                    InterruptEvent::parse(function, &attr, &resources)?;
+                grants.push(resources::grant(
+                    function,
+                    granted.into_iter().map(|(resource, _)| resource),
+                ));
+                interrupt_events.push(interrupt_event);
+            }
+        }
+    }
+    let bindings: Vec<(&Ident, &Ident)> = interrupts
+        .iter()
+        .map(|Interrupt { binds, handler }| (binds, handler))
+        .chain(
+            interrupt_events
+                .iter()
+                .map(|InterruptEvent { binds, handler, .. }| (binds, handler)),
+        )
+        .collect();
+    let device = match (device, bindings.first()) {
+        (Some(device), _) => Some(device),
+        (None, Some((_, handler))) => {
+            return Err(Error::new_spanned(
+                handler,
+                "`device` argument is required to bind interrupts, e.g. `device = pac`",
+            ))
+        }
+        (None, None) => None,
+    };
+    for (index, (binds, _)) in bindings.iter().enumerate() {
+        if bindings[..index].iter().any(|(other, _)| other == binds) {
+            return Err(Error::new_spanned(
+                binds,
+                format!("Interrupt {} is bound more than once", binds),
+            ));
+        }
+    }
+    for InterruptEvent {
+        binds,
+        task,
+        event,
+        mask,
+        ..
+    } in interrupt_events.iter()
+    {
+        let app_task = tasks
+            .iter_mut()
+            .find(|app_task| app_task.task.name.value() == task.value())
+            .ok_or_else(|| {
+                Error::new_spanned(
+                    task,
+                    format!(
+                        "Unknown task {}, expected a `#[task]` function",
+                        task.value()
+                    ),
+                )
+            })?;
+        if *mask {
+            app_task.unmask.push((binds.clone(), event.clone()));
+        }
+    }
+
+    let task_table: Vec<Task> = tasks.iter().map(|app_task| app_task.task.clone()).collect();
+    validate_tasks(
+        &LitInt::new(&tasks.len().to_string(), Span::call_site()),
+        &task_table,
+    )?;
+    if let Some(schedule_table) = schedule_table.as_ref() {
+        validate_schedule_table(schedule_table, &task_table)?;
+    }
+
+    let task_count = tasks.len();
+    let init = init
+        .map(|init| resources::path(&init))
+        .map(|init| quote!(#init();));
+    let idle = idle
+        .map(|idle| resources::path(&idle))
+        .map(|idle| quote!(scheduler.register_idle_runnable(#idle);));
+    let auto_offsets = auto_offsets.then(|| quote!(scheduler.enable_offset_assignment();));
+    let schedule_table = schedule_table.as_ref().map(ScheduleTable::expand);
+    for app_task in tasks
+        .iter_mut()
+        .filter(|app_task| !app_task.unmask.is_empty())
+    {
+        items.push(app_task.unmask_wrapper(&device));
+    }
+    resources.expand(items, &grants);
+    let tasks = tasks.iter().map(AppTask::expand);
+    let unmask = bindings.iter().map(|(binds, _)| {
+        quote! {
+            unsafe { ::cortex_m::peripheral::NVIC::unmask(#device::Interrupt::#binds) };
+        }
+    });
+    if !bindings.is_empty() {
+        let handlers = interrupts
+            .iter()
+            .map(|Interrupt { binds, handler }| {
+                let handler = resources::path(handler);
+                quote! {
+                    #[interrupt]
+                    fn #binds() {
+                        super::#handler();
+                    }
+                }
+            })
+            .chain(interrupt_events.iter().map(
+                |InterruptEvent {
+                     binds,
+                     task,
+                     event,
+                     mask,
+                     handler,
+                 }| {
+                    let handler = resources::path(handler);
+                    // Level triggered sources are masked until the task processes the event
+                    let mask = mask.then(|| {
+                        quote!(::cortex_m::peripheral::NVIC::mask(#device::Interrupt::#binds);)
+                    });
+                    quote! {
+                        #[interrupt]
+                        fn #binds() {
+                            super::#handler();
+                            #mask
+                            super::SCHEDULER.with(|scheduler| scheduler.set_task_event(#task, ::non_preemptive_scheduler::EventMask::from(#event)));
+                        }
+                    }
+                },
+            ));
+        items.push(parse_quote! {
+            #[doc(hidden)]
+            mod __interrupts {
+                use super::*;
+                use #device::interrupt;
+
+                #(#handlers)*
+            }
+        });
+    }
+    items.push(parse_quote! {
+        pub const TASK_COUNT: usize = #task_count;
+    });
+    let scheduler = match dispatch_policy {
+        Some(dispatch_policy) => quote! {
+            ::non_preemptive_scheduler::StaticScheduler::with_dispatch_policy(&#dispatch_policy)
+        },
+        None => quote!(::non_preemptive_scheduler::StaticScheduler::new()),
+    };
+    items.push(parse_quote! {
+        static SCHEDULER: ::non_preemptive_scheduler::StaticScheduler<TASK_COUNT, { #core_freq }> =
+            #scheduler;
+    });
+    items.push(parse_quote! {
+        #[::cortex_m_rt::entry]
+        fn main() -> ! {
+            #init
+            SCHEDULER.configure(|scheduler| {
+                #(#tasks)*
+                #idle
+                #auto_offsets
+                #schedule_table
+            });
+            #(#unmask)*
+            SCHEDULER.launch();
+            unreachable!("Not expected execution");
+        }
+    });
+
+    Ok(quote!(#module))
+}
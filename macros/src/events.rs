@@ -0,0 +1,70 @@
+//! Typed events which are mapped onto the bits of a task event mask
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Error, Fields, ItemEnum, Result};
+
+const EVENT_MASK_BITS: usize = 32;
+
+pub fn expand(item: ItemEnum) -> Result<TokenStream> {
+    if item.variants.len() > EVENT_MASK_BITS {
+        return Err(Error::new_spanned(
+            &item.ident,
+            format!(
+                "{} events declared but an event mask holds at most {}",
+                item.variants.len(),
+                EVENT_MASK_BITS
+            ),
+        ));
+    }
+    if !item.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &item.generics,
+            "Events cannot be generic",
+        ));
+    }
+    for variant in item.variants.iter() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(variant, "Events must be unit variants"));
+        }
+        if let Some((_, discriminant)) = &variant.discriminant {
+            return Err(Error::new_spanned(
+                discriminant,
+                "Event bits are assigned in declaration order, discriminants are not allowed",
+            ));
+        }
+    }
+
+    let ident = &item.ident;
+    let variants = item.variants.iter().map(|variant| &variant.ident);
+    let gen = quote! {
+        #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+        #item
+
+        impl ::non_preemptive_scheduler::events::Event for #ident {
+            const ALL: &'static [Self] = &[#(Self::#variants),*];
+
+            #[inline]
+            fn mask(self) -> ::non_preemptive_scheduler::EventMask {
+                1 << self as u32
+            }
+        }
+
+        impl From<#ident> for ::non_preemptive_scheduler::EventMask {
+            #[inline]
+            fn from(event: #ident) -> Self {
+                ::non_preemptive_scheduler::events::Event::mask(event)
+            }
+        }
+
+        impl ::core::ops::BitOr for #ident {
+            type Output = ::non_preemptive_scheduler::events::EventSet<#ident>;
+
+            #[inline]
+            fn bitor(self, other: Self) -> Self::Output {
+                ::non_preemptive_scheduler::events::EventSet::from(self) | other
+            }
+        }
+    };
+    Ok(gen)
+}
@@ -1,9 +1,17 @@
-//! Macros which deal with unsafe blocks when
-//! instantiating a static mutable Scheduler
+//! Macros which instantiate a static Scheduler singleton
+//! and give access to it without any unsafe block
+
+mod app;
+mod events;
+mod resources;
 
 use proc_macro::*;
 use quote::{quote, ToTokens};
-use syn::{parse::*, parse_macro_input, AttributeArgs, Expr, LitStr, Meta, NestedMeta, Token};
+use syn::{
+    bracketed, parenthesized, parse::*, parse_macro_input, punctuated::Punctuated, AttributeArgs,
+    Expr, ExprCall, ExprLit, Ident, ItemEnum, ItemMod, Lit, LitInt, LitStr, Meta, NestedMeta,
+    Token,
+};
 
 struct TaskEvent {
     task_name: LitStr,
@@ -19,6 +27,7 @@ impl Parse for TaskEvent {
     }
 }
 
+#[derive(Clone)]
 struct Task {
     name: LitStr,
     init_runnable: Expr,
@@ -48,6 +57,349 @@ impl Parse for Task {
     }
 }
 
+struct FallibleTask {
+    task: Task,
+    error_policy: Expr,
+}
+
+impl Parse for FallibleTask {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let task: Task = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let error_policy: Expr = input.parse()?;
+        Ok(FallibleTask { task, error_policy })
+    }
+}
+
+struct ExpiryPoint {
+    offset: LitInt,
+    tasks: Vec<LitStr>,
+}
+
+impl Parse for ExpiryPoint {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let offset: LitInt = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let content;
+        bracketed!(content in input);
+        let tasks: Punctuated<LitStr, Token![,]> =
+            content.parse_terminated(<LitStr as Parse>::parse)?;
+        Ok(ExpiryPoint {
+            offset,
+            tasks: tasks.into_iter().collect(),
+        })
+    }
+}
+
+struct ScheduleTable {
+    duration: LitInt,
+    expiry_points: Vec<ExpiryPoint>,
+}
+
+impl ScheduleTable {
+    /// Parses the parenthesized form `(<duration>, [(<offset>, [<task>, ..]), ..])`
+    fn parse_parenthesized(input: ParseStream) -> Result<Self> {
+        let content;
+        parenthesized!(content in input);
+        content.parse()
+    }
+
+    /// Declares the schedule table and registers it, to be expanded within the
+    /// configuration closure of the scheduler
+    fn expand(&self) -> proc_macro2::TokenStream {
+        let duration = &self.duration;
+        let expiry_points = self
+            .expiry_points
+            .iter()
+            .map(|ExpiryPoint { offset, tasks }| {
+                quote! {
+                    ::non_preemptive_scheduler::schedule::ExpiryPoint::new(#offset, &[#(#tasks),*])
+                }
+            });
+        quote! {
+            static SCHEDULE_TABLE: ::non_preemptive_scheduler::schedule::ScheduleTable =
+                ::non_preemptive_scheduler::schedule::ScheduleTable::new(#duration, &[#(#expiry_points),*]);
+            if let Err(error) = scheduler.register_schedule_table(&SCHEDULE_TABLE) {
+                panic!("Schedule table cannot be registered, {}", error);
+            }
+        }
+    }
+}
+
+impl Parse for ScheduleTable {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let duration: LitInt = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let content;
+        bracketed!(content in input);
+        let expiry_points: Punctuated<ExpiryPoint, Token![,]> =
+            content.parse_terminated(|input| {
+                let content;
+                parenthesized!(content in input);
+                content.parse::<ExpiryPoint>()
+            })?;
+        Ok(ScheduleTable {
+            duration,
+            expiry_points: expiry_points.into_iter().collect(),
+        })
+    }
+}
+
+struct TaskTable {
+    task_count: LitInt,
+    core_freq: LitInt,
+    tasks: Vec<Task>,
+    schedule_table: Option<ScheduleTable>,
+}
+
+impl Parse for TaskTable {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let (mut task_count, mut core_freq, mut tasks, mut schedule_table) =
+            (None, None, None, None);
+        while !input.is_empty() {
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match name.to_string().as_str() {
+                "task_count" => task_count = Some(input.parse()?),
+                "core_freq" => core_freq = Some(input.parse()?),
+                "tasks" => {
+                    let content;
+                    bracketed!(content in input);
+                    let punctuated: Punctuated<Task, Token![,]> =
+                        content.parse_terminated(|input| {
+                            let content;
+                            parenthesized!(content in input);
+                            content.parse::<Task>()
+                        })?;
+                    tasks = Some(punctuated.into_iter().collect());
+                }
+                "schedule_table" => {
+                    schedule_table = Some(ScheduleTable::parse_parenthesized(input)?)
+                }
+                _ => return Err(Error::new(name.span(), "Unrecognized argument")),
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(TaskTable {
+            task_count: task_count
+                .ok_or_else(|| input.error("`task_count` argument is required"))?,
+            core_freq: core_freq.ok_or_else(|| input.error("`core_freq` argument is required"))?,
+            tasks: tasks.ok_or_else(|| input.error("`tasks` argument is required"))?,
+            schedule_table,
+        })
+    }
+}
+
+/// Checks the task configuration which would otherwise panic at runtime
+fn validate_tasks(task_count: &LitInt, tasks: &[Task]) -> Result<()> {
+    let mut errors: Option<Error> = None;
+    let mut push_error = |error: Error| match errors.as_mut() {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
+    };
+
+    let capacity: usize = task_count.base10_parse()?;
+    if tasks.len() > capacity {
+        push_error(Error::new_spanned(
+            task_count,
+            format!(
+                "{} tasks declared but `task_count` is {}",
+                tasks.len(),
+                capacity
+            ),
+        ));
+    }
+
+    for (index, task) in tasks.iter().enumerate() {
+        if let Some(duplicate) = tasks
+            .iter()
+            .take(index)
+            .find(|other| other.name.value() == task.name.value())
+        {
+            push_error(Error::new_spanned(
+                &task.name,
+                format!("Task {} already declared", duplicate.name.value()),
+            ));
+        }
+        // Runnables bound to several tasks are rejected when adding the tasks at runtime
+        type RunnableOf = fn(&Task) -> &Expr;
+        let runnables: [(&str, RunnableOf); 2] = [
+            ("Init", |task| &task.init_runnable),
+            ("Process", |task| &task.process_runnable),
+        ];
+        for (kind, runnable_of) in runnables {
+            let runnable = runnable_of(task);
+            if is_none(runnable) {
+                continue;
+            }
+            let tokens = runnable.to_token_stream().to_string();
+            if let Some(other) = tasks
+                .iter()
+                .take(index)
+                .find(|other| runnable_of(other).to_token_stream().to_string() == tokens)
+            {
+                push_error(Error::new_spanned(
+                    runnable,
+                    format!(
+                        "{} runnable already bound to task {}",
+                        kind,
+                        other.name.value()
+                    ),
+                ));
+            }
+        }
+        if is_none(&task.process_runnable) && is_none(&task.execution_cycle) {
+            push_error(Error::new_spanned(
+                &task.name,
+                format!(
+                    "Task {} has neither a process runnable nor an execution cycle",
+                    task.name.value()
+                ),
+            ));
+        }
+        if some_int_literal(&task.execution_cycle) == Some(0) {
+            push_error(Error::new_spanned(
+                &task.execution_cycle,
+                "Execution cycle must be greater than 0",
+            ));
+        }
+    }
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(()),
+    }
+}
+
+/// Checks the schedule table layout and that its tasks are declared, have a process
+/// runnable and no execution cycle, which would otherwise be reported at runtime
+fn validate_schedule_table(schedule_table: &ScheduleTable, tasks: &[Task]) -> Result<()> {
+    let mut errors: Option<Error> = None;
+    let mut push_error = |error: Error| match errors.as_mut() {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
+    };
+
+    let duration: u64 = schedule_table.duration.base10_parse()?;
+    if duration == 0 {
+        push_error(Error::new_spanned(
+            &schedule_table.duration,
+            "Schedule table duration must be greater than 0",
+        ));
+    }
+
+    let mut previous_offset = None;
+    for expiry_point in schedule_table.expiry_points.iter() {
+        let offset: u64 = expiry_point.offset.base10_parse()?;
+        if offset >= duration {
+            push_error(Error::new_spanned(
+                &expiry_point.offset,
+                format!(
+                    "Expiry point offset {} is not lower than the schedule table duration {}",
+                    offset, duration
+                ),
+            ));
+        }
+        if previous_offset.is_some_and(|previous_offset| offset <= previous_offset) {
+            push_error(Error::new_spanned(
+                &expiry_point.offset,
+                "Expiry point offsets must be strictly increasing",
+            ));
+        }
+        previous_offset = Some(offset);
+
+        for name in expiry_point.tasks.iter() {
+            match tasks.iter().find(|task| task.name.value() == name.value()) {
+                None => push_error(Error::new_spanned(
+                    name,
+                    format!("Unknown task {}", name.value()),
+                )),
+                Some(task) if is_none(&task.process_runnable) => push_error(Error::new_spanned(
+                    name,
+                    format!("Task {} has no process runnable", name.value()),
+                )),
+                Some(task) if !is_none(&task.execution_cycle) => push_error(Error::new_spanned(
+                    name,
+                    format!(
+                        "Task {} has an execution cycle, which conflicts with the schedule table",
+                        name.value()
+                    ),
+                )),
+                Some(_) => (),
+            }
+        }
+    }
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(()),
+    }
+}
+
+/// Whether an expression is literally `None`
+fn is_none(expr: &Expr) -> bool {
+    matches!(expr, Expr::Path(path) if path.path.is_ident("None"))
+}
+
+/// Value of an expression in the form `Some(<integer literal>)`
+fn some_int_literal(expr: &Expr) -> Option<u64> {
+    match expr {
+        Expr::Call(ExprCall { func, args, .. }) if args.len() == 1 => match (&**func, &args[0]) {
+            (
+                Expr::Path(path),
+                Expr::Lit(ExprLit {
+                    lit: Lit::Int(value),
+                    ..
+                }),
+            ) if path.path.is_ident("Some") => value.base10_parse().ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Declares an application on a module, where tasks are functions annotated with
+/// `#[task(period = .., offset = .., init = .., error_policy = .., budget = .., priority = ..,
+/// resources = [..])]`,
+/// optionally along with an `#[init]` function executed before launching, an
+/// `#[idle(resources = [..])]` function and `#[interrupt(binds = .., resources = [..])]`
+/// handlers of the `device` interrupts.
+/// Interrupts can also be routed to task events with
+/// `#[interrupt_event(BINDS, task = "..", event = .., mask = .., resources = [..])]`
+/// on a function acknowledging the interrupt, the event is set on the task from the
+/// handler and, if `mask = true`, the interrupt stays masked until the task processes it.
+/// Statics of type `Shared`, `UnShared` or `Late` declared in the module are resources,
+/// only reachable from the functions which declare them, as both are moved into private
+/// scopes of the module where `super` designates the module. Resources which are not
+/// shared cannot be declared by interrupt handlers, and the annotated functions are only
+/// called by the generated code, so that no other handler of the module can reach them.
+/// With `auto_offsets = true`, the cyclic tasks declared without offset get one assigned
+/// at launch to spread the load. `dispatch_policy = ..` selects a policy of the
+/// `dispatch` module instead of the list order, e.g. `dispatch::FixedPriority`.
+/// `schedule_table = (<duration>, [(<offset>, [<task>, ..]), ..])` dispatches the listed
+/// tasks, declared without period, at each expiry point as with `tasks!`.
+/// The task count is derived from the declared tasks and `main` is generated
+#[proc_macro_attribute]
+pub fn app(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as app::Args);
+    let module = parse_macro_input!(input as ItemMod);
+    app::expand(args, module)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Declares the variants of a fieldless enum as task events, assigning a bit
+/// of the event mask to each of them in declaration order
+#[proc_macro_attribute]
+pub fn events(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as ItemEnum);
+    events::expand(item)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
 #[proc_macro_attribute]
 pub fn new(args: TokenStream, _input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as AttributeArgs);
@@ -78,21 +430,73 @@ pub fn new(args: TokenStream, _input: TokenStream) -> TokenStream {
     let task_count = task_count.expect("`task_count` argument is required");
     let core_freq = core_freq.expect("`core_freq` argument is required");
     let gen = quote! {
-        static mut SCHEDULER: Scheduler<#task_count, #core_freq> = Scheduler::<#task_count, #core_freq>::new();
+        static SCHEDULER: ::non_preemptive_scheduler::StaticScheduler<#task_count, #core_freq> =
+            ::non_preemptive_scheduler::StaticScheduler::new();
     };
     gen.into()
 }
 
+/// Declares the scheduler along with its whole task table, which is validated at
+/// compile time. Tasks are added to the scheduler by calling the generated `add_tasks`.
+/// An optional `schedule_table = (<duration>, [(<offset>, [<task>, ..]), ..])` dispatches
+/// the listed tasks at each expiry point, it is validated against the task table
 #[proc_macro]
-pub fn launch(_input: TokenStream) -> TokenStream {
+pub fn tasks(input: TokenStream) -> TokenStream {
+    let TaskTable {
+        task_count,
+        core_freq,
+        tasks,
+        schedule_table,
+    } = parse_macro_input!(input as TaskTable);
+    let mut result = validate_tasks(&task_count, &tasks);
+    if let Some(schedule_table) = schedule_table.as_ref() {
+        if let Err(error) = validate_schedule_table(schedule_table, &tasks) {
+            match result.as_mut() {
+                Err(errors) => errors.combine(error),
+                Ok(()) => result = Err(error),
+            }
+        }
+    }
+    if let Err(error) = result {
+        return error.to_compile_error().into();
+    }
+
+    let tasks = tasks.iter().map(
+        |Task {
+             name,
+             init_runnable,
+             process_runnable,
+             execution_cycle,
+             execution_offset,
+         }| {
+            quote! {
+                scheduler.add_task(::non_preemptive_scheduler::Task::new(#name, #init_runnable, #process_runnable, #execution_cycle, #execution_offset));
+            }
+        },
+    );
+    let schedule_table = schedule_table.as_ref().map(ScheduleTable::expand);
     let gen = quote! {
-        unsafe {
-            SCHEDULER.launch()
+        static SCHEDULER: ::non_preemptive_scheduler::StaticScheduler<#task_count, #core_freq> =
+            ::non_preemptive_scheduler::StaticScheduler::new();
+
+        fn add_tasks() {
+            SCHEDULER.configure(|scheduler| {
+                #(#tasks)*
+                #schedule_table
+            });
         }
     };
     gen.into()
 }
 
+#[proc_macro]
+pub fn launch(_input: TokenStream) -> TokenStream {
+    let gen = quote! {
+        SCHEDULER.launch()
+    };
+    gen.into()
+}
+
 #[proc_macro]
 pub fn add_task(input: TokenStream) -> TokenStream {
     let Task {
@@ -103,10 +507,45 @@ pub fn add_task(input: TokenStream) -> TokenStream {
         execution_offset,
     } = parse_macro_input!(input as Task);
     let gen = quote! {
-        let task = Task::new(#name, #init_runnable, #process_runnable, #execution_cycle, #execution_offset);
-        unsafe {
-            SCHEDULER.add_task(task);
-        }
+        let task = ::non_preemptive_scheduler::Task::new(#name, #init_runnable, #process_runnable, #execution_cycle, #execution_offset);
+        SCHEDULER.configure(|scheduler| scheduler.add_task(task));
+    };
+    gen.into()
+}
+
+#[proc_macro]
+pub fn try_add_task(input: TokenStream) -> TokenStream {
+    let Task {
+        name,
+        init_runnable,
+        process_runnable,
+        execution_cycle,
+        execution_offset,
+    } = parse_macro_input!(input as Task);
+    let gen = quote! {
+        SCHEDULER.configure(|scheduler| {
+            scheduler.try_add_task(::non_preemptive_scheduler::Task::new(#name, #init_runnable, #process_runnable, #execution_cycle, #execution_offset))
+        })
+    };
+    gen.into()
+}
+
+#[proc_macro]
+pub fn add_fallible_task(input: TokenStream) -> TokenStream {
+    let FallibleTask {
+        task:
+            Task {
+                name,
+                init_runnable,
+                process_runnable,
+                execution_cycle,
+                execution_offset,
+            },
+        error_policy,
+    } = parse_macro_input!(input as FallibleTask);
+    let gen = quote! {
+        let task = ::non_preemptive_scheduler::Task::new_fallible(#name, #init_runnable, #process_runnable, #execution_cycle, #execution_offset, #error_policy);
+        SCHEDULER.configure(|scheduler| scheduler.add_task(task));
     };
     gen.into()
 }
@@ -115,30 +554,112 @@ pub fn add_task(input: TokenStream) -> TokenStream {
 pub fn register_idle_runnable(input: TokenStream) -> TokenStream {
     let idle_runnable = parse_macro_input!(input as Expr);
     let gen = quote! {
-        unsafe {
-            SCHEDULER.register_idle_runnable(#idle_runnable);
+        SCHEDULER.configure(|scheduler| scheduler.register_idle_runnable(#idle_runnable));
+    };
+    gen.into()
+}
+
+#[proc_macro]
+pub fn register_fault_hook(input: TokenStream) -> TokenStream {
+    let fault_hook = parse_macro_input!(input as Expr);
+    let gen = quote! {
+        SCHEDULER.configure(|scheduler| scheduler.register_fault_hook(#fault_hook));
+    };
+    gen.into()
+}
+
+#[proc_macro]
+pub fn register_overrun_hook(input: TokenStream) -> TokenStream {
+    let overrun_hook = parse_macro_input!(input as Expr);
+    let gen = quote! {
+        SCHEDULER.configure(|scheduler| scheduler.register_overrun_hook(#overrun_hook));
+    };
+    gen.into()
+}
+
+#[proc_macro]
+pub fn register_trace_sink(input: TokenStream) -> TokenStream {
+    let trace_sink = parse_macro_input!(input as Expr);
+    let gen = quote! {
+        SCHEDULER.configure(|scheduler| scheduler.register_trace_sink(#trace_sink));
+    };
+    gen.into()
+}
+
+#[proc_macro]
+pub fn enable_offset_assignment(_input: TokenStream) -> TokenStream {
+    let gen = quote! {
+        SCHEDULER.configure(|scheduler| scheduler.enable_offset_assignment());
+    };
+    gen.into()
+}
+
+/// Registers the modes of the application, to be invoked once the tasks are added
+#[proc_macro]
+pub fn register_modes(input: TokenStream) -> TokenStream {
+    let modes = parse_macro_input!(input as Expr);
+    let gen = quote! {
+        SCHEDULER.configure(|scheduler| scheduler.register_modes(#modes))
+    };
+    gen.into()
+}
+
+/// Requests a mode switch, `None` if the scheduler is being configured
+#[proc_macro]
+pub fn switch_mode(input: TokenStream) -> TokenStream {
+    let mode_name = parse_macro_input!(input as LitStr);
+    let gen = quote! {
+        SCHEDULER.with(|scheduler| scheduler.switch_mode(#mode_name))
+    };
+    gen.into()
+}
+
+#[proc_macro]
+pub fn suspend_task(input: TokenStream) -> TokenStream {
+    let task_name = parse_macro_input!(input as LitStr);
+    let gen = quote! {
+        {
+            SCHEDULER.with(|scheduler| scheduler.suspend_task(#task_name));
+        }
+    };
+    gen.into()
+}
+
+#[proc_macro]
+pub fn resume_task(input: TokenStream) -> TokenStream {
+    let task_name = parse_macro_input!(input as LitStr);
+    let gen = quote! {
+        {
+            SCHEDULER.with(|scheduler| scheduler.resume_task(#task_name));
         }
     };
     gen.into()
 }
 
+/// Sets events on a task, given as a mask or as events declared with `#[events]`, either a
+/// single variant or a set of them. The events are dropped if the scheduler is being configured
 #[proc_macro]
 pub fn set_task_event(input: TokenStream) -> TokenStream {
     let TaskEvent { task_name, event } = parse_macro_input!(input as TaskEvent);
     let gen = quote! {
-        unsafe {
-            SCHEDULER.set_task_event(#task_name, #event);
+        {
+            SCHEDULER.with(|scheduler| {
+                scheduler.set_task_event(#task_name, ::non_preemptive_scheduler::EventMask::from(#event))
+            });
         }
     };
     gen.into()
 }
 
+/// Clears events of a task, given as for [`set_task_event!`]
 #[proc_macro]
 pub fn clear_task_event(input: TokenStream) -> TokenStream {
     let TaskEvent { task_name, event } = parse_macro_input!(input as TaskEvent);
     let gen = quote! {
-        unsafe {
-            SCHEDULER.clear_task_event(#task_name, #event)
+        {
+            SCHEDULER.with(|scheduler| {
+                scheduler.clear_task_event(#task_name, ::non_preemptive_scheduler::EventMask::from(#event))
+            });
         }
     };
     gen.into()
@@ -148,9 +669,9 @@ pub fn clear_task_event(input: TokenStream) -> TokenStream {
 pub fn get_task_event(input: TokenStream) -> TokenStream {
     let task_name = parse_macro_input!(input as LitStr);
     let gen = quote! {
-        unsafe {
-            SCHEDULER.get_task_event(#task_name)
-        }
+        SCHEDULER
+            .with(|scheduler| scheduler.get_task_event(#task_name))
+            .flatten()
     };
     gen.into()
 }
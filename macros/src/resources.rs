@@ -0,0 +1,223 @@
+//! Resource analysis of a declarative application, each function only gets
+//! access to the resources it declares and resources which are not shared
+//! can only be declared by functions running in the super loop
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{parse_quote, Error, Expr, Ident, Item, ItemFn, ItemStatic, Result, Type, Visibility};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// `Shared`, accessed within a critical section from any execution context
+    Shared,
+    /// `UnShared` or `Late`, accessed only from the super loop
+    UnShared,
+}
+
+pub struct Resource {
+    ident: Ident,
+    kind: Kind,
+    item: ItemStatic,
+}
+
+/// Function granted access to resources
+pub struct Grant {
+    function: Ident,
+    resources: Vec<Ident>,
+}
+
+pub struct Resources(Vec<Resource>);
+
+impl Resources {
+    /// Takes the resource statics out of the application, they are placed back
+    /// into private scopes by [`Resources::expand`]
+    pub fn collect(items: &mut Vec<Item>) -> Self {
+        let mut resources = Vec::new();
+        items.retain(|item| match item {
+            Item::Static(item) => match kind_of(&item.ty) {
+                Some(kind) => {
+                    resources.push(Resource {
+                        ident: item.ident.clone(),
+                        kind,
+                        item: item.clone(),
+                    });
+                    false
+                }
+                None => true,
+            },
+            _ => true,
+        });
+        Resources(resources)
+    }
+
+    /// Parses a resource list in the form `[RESOURCE_A, RESOURCE_B]`, resources
+    /// are returned along with the span where they are declared in the list
+    pub fn parse_list(&self, value: &Expr) -> Result<Vec<(&Resource, Span)>> {
+        let array = match value {
+            Expr::Array(array) => array,
+            _ => return Err(Error::new_spanned(value, "Expected a list of resources")),
+        };
+        array
+            .elems
+            .iter()
+            .map(|elem| {
+                let ident = match elem {
+                    Expr::Path(path) => path.path.get_ident(),
+                    _ => None,
+                }
+                .ok_or_else(|| Error::new_spanned(elem, "Expected a resource name"))?;
+                self.0
+                    .iter()
+                    .find(|resource| resource.ident == *ident)
+                    .map(|resource| (resource, ident.span()))
+                    .ok_or_else(|| {
+                        Error::new_spanned(
+                            ident,
+                            format!(
+                                "Unknown resource {}, resources are statics of type \
+                                 `Shared`, `UnShared` or `Late` declared in the application",
+                                ident
+                            ),
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    pub fn all(&self) -> impl DoubleEndedIterator<Item = &Resource> {
+        self.0.iter()
+    }
+}
+
+impl Resource {
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn ident(&self) -> &Ident {
+        &self.ident
+    }
+}
+
+/// Grants access to the resources by binding them at the beginning of the function,
+/// each binding is obtained from an accessor of the resource which requires a grant
+/// only the scope of the function can create
+pub fn grant<'a>(
+    function: &mut ItemFn,
+    resources: impl DoubleEndedIterator<Item = &'a Resource>,
+) -> Grant {
+    let function_ident = &function.sig.ident;
+    let mut granted = Vec::new();
+    for resource in resources.rev() {
+        let ident = &resource.ident;
+        let module = hidden(ident);
+        function.block.stmts.insert(
+            0,
+            parse_quote! {
+                #[allow(non_snake_case, unused_variables)]
+                let #ident = super::#module::#function_ident(&__Grant(()));
+            },
+        );
+        granted.push(ident.clone());
+    }
+    Grant {
+        function: function_ident.clone(),
+        resources: granted,
+    }
+}
+
+impl Resources {
+    /// Moves each annotated function into a private scope along with its grant, then each
+    /// resource into a private scope along with an accessor per function declaring it, so
+    /// that the resources are not reachable from the rest of the module. Functions are not
+    /// imported back into the module, only the generated code reaches them through [`path`],
+    /// hence a function granted resources which are not shared cannot be called from an
+    /// interrupt handler of the module
+    pub fn expand(self, items: &mut Vec<Item>, grants: &[Grant]) {
+        for item in items.iter_mut() {
+            let function = match item {
+                Item::Fn(function)
+                    if grants
+                        .iter()
+                        .any(|grant| grant.function == function.sig.ident) =>
+                {
+                    function
+                }
+                _ => continue,
+            };
+            let scope = scope(&function.sig.ident);
+            function.vis = parse_quote!(pub(super));
+            *item = parse_quote! {
+                #[doc(hidden)]
+                mod #scope {
+                    use super::*;
+
+                    #[allow(dead_code)]
+                    pub struct __Grant(());
+
+                    #function
+                }
+            };
+        }
+
+        for Resource {
+            ident, mut item, ..
+        } in self.0
+        {
+            let module = hidden(&ident);
+            let ty = &item.ty;
+            // Hidden lifetimes of the type are elided to the lifetime of the grant
+            let accessors = grants
+                .iter()
+                .filter(|grant| grant.resources.contains(&ident))
+                .map(|Grant { function, .. }| {
+                    let scope = scope(function);
+                    quote! {
+                        #[inline(always)]
+                        #[allow(unknown_lints, mismatched_lifetime_syntaxes)]
+                        pub fn #function(_: &'static super::#scope::__Grant) -> &'static #ty {
+                            &RESOURCE
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+            item.ident = format_ident!("RESOURCE", span = ident.span());
+            item.vis = Visibility::Inherited;
+            items.push(parse_quote! {
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                mod #module {
+                    use super::*;
+
+                    #item
+                    #(#accessors)*
+                }
+            });
+        }
+    }
+}
+
+/// Path of an annotated function from the application module, once moved into its scope
+pub fn path(function: &Ident) -> TokenStream {
+    let scope = scope(function);
+    quote!(#scope::#function)
+}
+
+fn hidden(ident: &Ident) -> Ident {
+    format_ident!("__{}_RESOURCE", ident, span = Span::call_site())
+}
+
+fn scope(function: &Ident) -> Ident {
+    format_ident!("__{}_scope", function, span = Span::call_site())
+}
+
+fn kind_of(ty: &Type) -> Option<Kind> {
+    match ty {
+        Type::Path(path) => match path.path.segments.last()?.ident.to_string().as_str() {
+            "Shared" => Some(Kind::Shared),
+            "UnShared" | "Late" => Some(Kind::UnShared),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,8 @@
+//! Compile-time validation of the macros, each case is expected to fail with the
+//! error message recorded next to it
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,10 @@
+#[non_preemptive_scheduler_macros::app(
+    core_freq = 100_000_000,
+    schedule_table = (10, [(0, ["cyclic_task"])]),
+)]
+mod app {
+    #[task(period = 10)]
+    fn cyclic_task(_event_mask: u32) {}
+}
+
+fn main() {}
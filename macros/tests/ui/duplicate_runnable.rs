@@ -0,0 +1,15 @@
+use non_preemptive_scheduler_macros::tasks;
+
+fn init() {}
+fn process(_event_mask: u32) {}
+
+tasks! {
+    task_count = 2,
+    core_freq = 100_000_000,
+    tasks = [
+        ("first_task", Some(init), Some(process), Some(10), None),
+        ("second_task", Some(init), Some(process), Some(20), None),
+    ],
+}
+
+fn main() {}
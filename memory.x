@@ -10,3 +10,5 @@ MEMORY
 }
 
 _stack_start = ORIGIN(RAM) + LENGTH(RAM); 
+/* Lowest address of the stack, painted down to by the stack monitor */
+_stack_limit = _stack_start - 32K;
@@ -0,0 +1,286 @@
+//! Offline schedulability analysis of the cyclic tasks, the super loop is simulated
+//! over the hyperperiod with the worst-case execution time of every dispatch, which
+//! gives the worst-case response time and jitter of each task on the 1 ms tick
+
+use crate::Scheduler;
+use std::{fmt, vec::Vec};
+
+/// Longest hyperperiod in milliseconds which is simulated
+pub const MAX_HYPERPERIOD: u64 = 3_600_000;
+
+/// Timing of a cyclic task, in the order of the task list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskParameters<'a> {
+    pub name: &'a str,
+    /// Execution cycle in milliseconds
+    pub period: u32,
+    /// Execution offset in milliseconds
+    pub offset: u32,
+    /// Worst-case execution time in microseconds
+    pub wcet: u32,
+}
+
+/// Worst-case timing of a task over the simulation, times in microseconds relative
+/// to the tick at which each execution is due
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskAnalysis<'a> {
+    pub name: &'a str,
+    pub executions: u32,
+    /// Longest time from the due tick to the end of the execution
+    pub worst_response_time: u32,
+    /// Longest time the task waited for other tasks before being dispatched
+    pub worst_blocking: u32,
+    /// Difference between the longest and the shortest dispatch latency
+    pub jitter: u32,
+    /// Executions not finished within a period from their due tick
+    pub deadline_misses: u32,
+}
+
+impl TaskAnalysis<'_> {
+    #[inline]
+    pub fn is_schedulable(&self) -> bool {
+        self.deadline_misses == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis<'a> {
+    /// Least common multiple of the periods in milliseconds
+    pub hyperperiod: u64,
+    pub tasks: Vec<TaskAnalysis<'a>>,
+}
+
+impl Analysis<'_> {
+    /// Whether no task can miss its deadline
+    pub fn is_schedulable(&self) -> bool {
+        self.tasks.iter().all(TaskAnalysis::is_schedulable)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalysisError {
+    /// Period configured to zero
+    InvalidPeriod,
+    /// Hyperperiod longer than [`MAX_HYPERPERIOD`]
+    HyperperiodTooLong,
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::InvalidPeriod => write!(f, "period must be greater than 0"),
+            AnalysisError::HyperperiodTooLong => write!(
+                f,
+                "hyperperiod longer than {} ms cannot be simulated",
+                MAX_HYPERPERIOD
+            ),
+        }
+    }
+}
+
+/// Parameters of the cyclic tasks added to the scheduler, with their longest measured
+/// execution as worst-case execution time
+pub fn task_set<const TASK_COUNT: usize, const CORE_FREQ: u32>(
+    scheduler: &Scheduler<TASK_COUNT, CORE_FREQ>,
+) -> Vec<TaskParameters<'static>> {
+    scheduler
+        .tasks()
+        .filter_map(|info| {
+            info.next_deadline
+                .and(info.period)
+                .map(|period| TaskParameters {
+                    name: info.name,
+                    period,
+                    offset: info.offset.unwrap_or(0),
+                    wcet: info.max_execution_time,
+                })
+        })
+        .collect()
+}
+
+/// Simulates the super loop from launch over two hyperperiods past the largest offset,
+/// so that the drift of delayed cycles settles. Every dispatch takes the worst-case
+/// execution time and the loop idles until the next tick whenever no task is due
+pub fn analyse<'a>(tasks: &[TaskParameters<'a>]) -> Result<Analysis<'a>, AnalysisError> {
+    let mut hyperperiod: u64 = 1;
+    for task in tasks {
+        if task.period == 0 {
+            return Err(AnalysisError::InvalidPeriod);
+        }
+        hyperperiod = lcm(hyperperiod, task.period as u64);
+        if hyperperiod > MAX_HYPERPERIOD {
+            return Err(AnalysisError::HyperperiodTooLong);
+        }
+    }
+    let max_offset = tasks.iter().map(|task| task.offset as u64).max();
+    let horizon = max_offset.unwrap_or(0) + 2 * hyperperiod;
+
+    struct State {
+        deadline: u64,
+        min_latency: u64,
+        analysis: TaskAnalysis<'static>,
+    }
+    let mut states: Vec<State> = tasks
+        .iter()
+        .map(|task| State {
+            deadline: task.period as u64 + task.offset as u64,
+            min_latency: u64::MAX,
+            analysis: TaskAnalysis {
+                name: "",
+                executions: 0,
+                worst_response_time: 0,
+                worst_blocking: 0,
+                jitter: 0,
+                deadline_misses: 0,
+            },
+        })
+        .collect();
+
+    // Time in microseconds, each pass is performed with the tick at its start
+    let mut time: u64 = 0;
+    while time / 1_000 < horizon {
+        let tick = time / 1_000;
+        let mut task_execution = false;
+        for (task, state) in tasks.iter().zip(states.iter_mut()) {
+            if tick < state.deadline {
+                continue;
+            }
+            let (release, period) = (state.deadline * 1_000, task.period as u64);
+            let latency = time - release;
+            time += task.wcet as u64;
+            let response_time = time - release;
+
+            let analysis = &mut state.analysis;
+            analysis.executions += 1;
+            analysis.worst_response_time = analysis.worst_response_time.max(response_time as u32);
+            analysis.worst_blocking = analysis.worst_blocking.max(latency as u32);
+            state.min_latency = state.min_latency.min(latency);
+            analysis.jitter = analysis.worst_blocking - state.min_latency as u32;
+            if response_time > period * 1_000 {
+                analysis.deadline_misses += 1;
+            }
+            // The next cycle is due a period after the dispatch tick, as in the scheduler
+            state.deadline = tick + period;
+            task_execution = true;
+        }
+        // The loop idles until the next tick at which a task is due
+        if !task_execution {
+            let next_deadline = states.iter().map(|state| state.deadline).min();
+            time = next_deadline.unwrap_or(horizon).max(tick + 1) * 1_000;
+        }
+    }
+
+    Ok(Analysis {
+        hyperperiod,
+        tasks: tasks
+            .iter()
+            .zip(states)
+            .map(|(task, state)| TaskAnalysis {
+                name: task.name,
+                ..state.analysis
+            })
+            .collect(),
+    })
+}
+
+fn lcm(a: u64, b: u64) -> u64 {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    a / x * b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedulable_task_set() {
+        let tasks = [
+            TaskParameters {
+                name: "Fast task",
+                period: 2,
+                offset: 0,
+                wcet: 300,
+            },
+            TaskParameters {
+                name: "Slow task",
+                period: 5,
+                offset: 1,
+                wcet: 1_200,
+            },
+        ];
+        let analysis = analyse(&tasks).unwrap();
+        assert_eq!(analysis.hyperperiod, 10);
+        assert!(analysis.is_schedulable());
+
+        // Fast task is due at tick 12 while the slow task dispatched at tick 11 executes
+        let fast = analysis.tasks[0];
+        assert_eq!(fast.name, "Fast task");
+        assert_eq!(fast.executions, 10);
+        assert_eq!(fast.worst_blocking, 200);
+        assert_eq!(fast.worst_response_time, 500);
+
+        // Slow task is due at tick 6 along with the fast task, which is dispatched first
+        let slow = analysis.tasks[1];
+        assert_eq!(slow.worst_blocking, 300);
+        assert_eq!(slow.worst_response_time, 1_500);
+        assert_eq!(slow.jitter, 300);
+    }
+
+    #[test]
+    fn blocking_deadline_miss() {
+        let tasks = [
+            TaskParameters {
+                name: "Long task",
+                period: 10,
+                offset: 0,
+                wcet: 2_500,
+            },
+            TaskParameters {
+                name: "Short task",
+                period: 2,
+                offset: 0,
+                wcet: 100,
+            },
+        ];
+        let analysis = analyse(&tasks).unwrap();
+        assert!(!analysis.is_schedulable());
+        assert!(analysis.tasks[0].is_schedulable());
+
+        let short = analysis.tasks[1];
+        assert_eq!(short.worst_blocking, 2_500);
+        assert_eq!(short.worst_response_time, 2_600);
+        assert!(short.deadline_misses > 0);
+    }
+
+    #[test]
+    fn analysis_errors() {
+        let task = TaskParameters {
+            name: "Dummy task",
+            period: 0,
+            offset: 0,
+            wcet: 0,
+        };
+        assert_eq!(analyse(&[task]), Err(AnalysisError::InvalidPeriod));
+
+        let tasks = [1_999_993, 1_999_999].map(|period| TaskParameters { period, ..task });
+        assert_eq!(analyse(&tasks), Err(AnalysisError::HyperperiodTooLong));
+    }
+
+    #[test]
+    fn offset_near_limit() {
+        let task = TaskParameters {
+            name: "Late task",
+            period: 10,
+            offset: u32::MAX - 5,
+            wcet: 100,
+        };
+        let analysis = analyse(&[task]).unwrap();
+        // First execution is due past the range of u32, within the simulated horizon
+        assert_eq!(analysis.tasks[0].executions, 1);
+        assert_eq!(analysis.tasks[0].worst_response_time, 100);
+        assert!(analysis.is_schedulable());
+    }
+}
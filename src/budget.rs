@@ -0,0 +1,66 @@
+//! Execution budget of the tasks, armed when a task is dispatched and checked
+//! from the tick interrupt, so a runaway runnable is reported while it executes.
+//! There is no dedicated timer, the check is polled by the 1 ms SysTick handler, hence
+//! an overrun is detected up to 1 ms after the budget is exceeded. Runnables being
+//! cooperative, the overrun cannot be stopped otherwise than by resetting the system.
+//! The x86 port has no tick interrupt, budgets are armed but never checked
+
+use crate::{port, resources::Shared, TaskName};
+
+/// Action requested by the overrun hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OverrunAction {
+    /// Keep executing the runnable, the overrun is only reported once per execution
+    Continue,
+    /// Reset the system
+    Reset,
+}
+
+/// Hook invoked from the tick interrupt with the name of the task exceeding its budget
+pub type OverrunHook = fn(TaskName) -> OverrunAction;
+
+#[cfg_attr(not(any(test, feature = "armvx-m")), allow(dead_code))]
+struct Armed {
+    task: TaskName,
+    start: u32,
+    budget: u32,
+}
+
+static ARMED: Shared<Option<Armed>> = Shared::new(None);
+static HOOK: Shared<Option<OverrunHook>> = Shared::new(None);
+
+pub(crate) fn register(hook: OverrunHook) {
+    HOOK.lock(|current| *current = Some(hook));
+}
+
+/// Arms the budget in milliseconds of the task about to be executed
+pub(crate) fn arm(task: TaskName, budget: u32) {
+    let armed = Armed {
+        task,
+        start: port::now_us(),
+        budget: budget.saturating_mul(1_000),
+    };
+    ARMED.lock(|current| *current = Some(armed));
+}
+
+pub(crate) fn disarm() {
+    ARMED.lock(|current| *current = None);
+}
+
+/// Checks whether the armed budget is exceeded, called by the port upon every tick
+#[cfg(any(test, feature = "armvx-m"))]
+pub(crate) fn check() {
+    let now = port::now_us();
+    let overrun = ARMED.lock(|armed| match armed {
+        Some(Armed { start, budget, .. }) if now.wrapping_sub(*start) >= *budget => {
+            armed.take().map(|armed| armed.task)
+        }
+        _ => None,
+    });
+    if let (Some(task), Some(hook)) = (overrun, HOOK.lock(|hook| *hook)) {
+        if hook(task) == OverrunAction::Reset {
+            port::reset();
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Dispatch policies, which order the tasks visited by each pass of the super loop.
+//! A policy is selected when constructing the scheduler, see
+//! [`Scheduler::with_dispatch_policy`](crate::Scheduler::with_dispatch_policy)
+
+use crate::TaskName;
+
+/// Task visited by a pass of the super loop, it is dispatched if it is due or has pending events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DispatchCandidate {
+    /// Position in the task list
+    pub index: usize,
+    pub name: TaskName,
+    /// Priority of the task, the higher the more urgent
+    pub priority: u8,
+    /// Tick of the next cyclic execution, if the task is cyclic
+    pub deadline: Option<u32>,
+}
+
+pub trait DispatchPolicy: Sync {
+    /// Sorts the candidates into the order in which they are visited
+    fn order(&self, candidates: &mut [DispatchCandidate]);
+
+    /// Whether a new pass is started after each dispatch, so that a task which became
+    /// ready meanwhile is considered before the remaining ones of the pass
+    fn restart_after_dispatch(&self) -> bool {
+        true
+    }
+
+    /// Whether `order` leaves the candidates in list order, in which case the super loop
+    /// walks the tasks directly without building the candidates
+    fn keeps_list_order(&self) -> bool {
+        false
+    }
+}
+
+/// Tasks are visited in the order they were added and every ready task is dispatched
+/// within the pass, the default policy
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListOrder;
+
+impl DispatchPolicy for ListOrder {
+    #[inline]
+    fn order(&self, _candidates: &mut [DispatchCandidate]) {}
+
+    #[inline]
+    fn restart_after_dispatch(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn keeps_list_order(&self) -> bool {
+        true
+    }
+}
+
+/// Highest priority ready task first, tasks of same priority in list order
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedPriority;
+
+impl DispatchPolicy for FixedPriority {
+    fn order(&self, candidates: &mut [DispatchCandidate]) {
+        candidates
+            .sort_unstable_by_key(|candidate| (u8::MAX - candidate.priority, candidate.index));
+    }
+}
+
+/// Non-preemptive earliest deadline first, the deadline of a cyclic task being the tick of
+/// its next execution. Tasks which are not cyclic come last, in list order
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EarliestDeadlineFirst;
+
+impl DispatchPolicy for EarliestDeadlineFirst {
+    fn order(&self, candidates: &mut [DispatchCandidate]) {
+        candidates.sort_unstable_by_key(|candidate| {
+            (
+                candidate.deadline.is_none(),
+                candidate.deadline,
+                candidate.index,
+            )
+        });
+    }
+}
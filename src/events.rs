@@ -0,0 +1,159 @@
+//! Typed task events, usually declared through the `#[events]` macro
+//! which assigns a bit of the task event mask to each variant
+
+use crate::EventMask;
+use core::{fmt, marker::PhantomData, ops::BitOr};
+
+/// Event which can be set on a task, each event owns a single bit of the event mask
+pub trait Event: Copy + Eq + fmt::Debug + 'static {
+    /// All the events of the set, in bit order
+    const ALL: &'static [Self];
+
+    fn mask(self) -> EventMask;
+}
+
+/// Set of events of the same kind, as received by a process runnable
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct EventSet<E: Event> {
+    mask: EventMask,
+    _marker: PhantomData<E>,
+}
+
+impl<E: Event> EventSet<E> {
+    pub const fn empty() -> Self {
+        EventSet {
+            mask: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Builds the set out of a raw event mask, bits not assigned to any event are discarded
+    pub fn from_mask(mask: EventMask) -> Self {
+        let known = E::ALL.iter().fold(0, |known, event| known | event.mask());
+        EventSet {
+            mask: mask & known,
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn mask(&self) -> EventMask {
+        self.mask
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    #[inline]
+    pub fn contains(&self, event: E) -> bool {
+        self.mask & event.mask() != 0
+    }
+
+    /// Iterates over the events contained in the set, in bit order
+    pub fn iter(&self) -> impl Iterator<Item = E> {
+        let mask = self.mask;
+        E::ALL
+            .iter()
+            .copied()
+            .filter(move |event| mask & event.mask() != 0)
+    }
+}
+
+impl<E: Event> Default for EventSet<E> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<E: Event> fmt::Debug for EventSet<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<E: Event> From<E> for EventSet<E> {
+    fn from(event: E) -> Self {
+        EventSet {
+            mask: event.mask(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<E: Event> From<EventSet<E>> for EventMask {
+    fn from(events: EventSet<E>) -> Self {
+        events.mask
+    }
+}
+
+impl<E: Event> BitOr<E> for EventSet<E> {
+    type Output = Self;
+
+    fn bitor(self, event: E) -> Self {
+        EventSet {
+            mask: self.mask | event.mask(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum DummyEvents {
+        First,
+        Second,
+        Third,
+    }
+
+    impl Event for DummyEvents {
+        const ALL: &'static [Self] = &[Self::First, Self::Second, Self::Third];
+
+        fn mask(self) -> EventMask {
+            1 << self as u32
+        }
+    }
+
+    #[non_preemptive_scheduler_macros::events]
+    enum MacroEvents {
+        First,
+        Second,
+        Third,
+    }
+
+    #[test]
+    fn events_macro_expansion() {
+        assert_eq!(
+            MacroEvents::ALL,
+            [MacroEvents::First, MacroEvents::Second, MacroEvents::Third]
+        );
+        assert_eq!(EventMask::from(MacroEvents::First), 0x00000001);
+        assert_eq!(MacroEvents::Third.mask(), 0x00000004);
+
+        let events: EventSet<MacroEvents> = MacroEvents::Second | MacroEvents::Third;
+        assert_eq!(events.mask(), 0x00000006);
+        assert_eq!(
+            EventSet::<MacroEvents>::from_mask(0x00000003),
+            EventSet::from(MacroEvents::First) | MacroEvents::Second
+        );
+    }
+
+    #[test]
+    fn event_set_handling() {
+        let events = EventSet::from(DummyEvents::First) | DummyEvents::Third;
+        assert_eq!(EventMask::from(events), 0x00000005);
+        assert!(events.contains(DummyEvents::Third));
+        assert!(!events.contains(DummyEvents::Second));
+        assert!(events
+            .iter()
+            .eq([DummyEvents::First, DummyEvents::Third].into_iter()));
+
+        let events = EventSet::<DummyEvents>::from_mask(0xFFFFFFF2);
+        assert_eq!(events.mask(), 0x00000002);
+        assert!(EventSet::<DummyEvents>::from_mask(0x00000008).is_empty());
+    }
+}
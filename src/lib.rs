@@ -2,7 +2,11 @@
 //! Basic non-preemptive scheduler to control task execution upon cycle completion
 //! and external events which could fit on basic applications
 
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+// Code generated by the macros refers to the crate by name, which tests expand in here
+#[cfg(test)]
+extern crate self as non_preemptive_scheduler;
 
 #[cfg(not(feature = "core"))]
 compile_error!(
@@ -11,35 +15,197 @@ compile_error!(
 "
 );
 
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "budget")]
+mod budget;
+pub mod dispatch;
+pub mod events;
+pub mod modes;
+mod offsets;
 mod port;
 pub mod resources;
+pub mod schedule;
+#[cfg(feature = "shell")]
+pub mod shell;
+mod singleton;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod trace;
 
-use core::str;
+#[cfg(feature = "budget")]
+pub use budget::{OverrunAction, OverrunHook};
+pub use singleton::{SchedulerHandle, StaticScheduler};
+
+use core::{
+    cell::Cell,
+    fmt, str,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use dispatch::{DispatchCandidate, DispatchPolicy, ListOrder};
+use events::{Event, EventSet};
 use heapless::Vec;
-#[cfg(debug_assertions)]
-use port::log;
-use port::{critical_section, SysTick};
+use modes::{Mode, ModeName};
+use port::SysTick;
+use port::{log_error, log_info, log_trace};
+use resources::Shared;
+use schedule::ScheduleTable;
+use trace::{Cause, TraceEvent, TraceSink};
 
 pub type InitRunnable = fn();
 pub type ProcessRunnable = fn(u32);
+pub type FallibleProcessRunnable = fn(u32) -> Result<(), TaskError>;
 pub type IdleRunnable = fn();
+pub type FaultHook = fn(TaskName, TaskError);
 pub type TaskName = &'static str;
 pub type EventMask = u32;
 type TaskList<const N: usize> = Vec<Task, N>;
 
+/// Error reported by a fallible process runnable, the code is application defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TaskError(pub u32);
+
+/// Configuration errors reported when adding a task to the scheduler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SchedulerError {
+    /// Task has either same name, init runnable or process runnable than an already added task
+    Duplicate { existing: TaskName },
+    /// Task list already holds `TASK_COUNT` tasks
+    Full,
+    /// Execution cycle configured to zero
+    InvalidCycle,
+    /// No task with the given name
+    UnknownTask,
+    /// Execution cycle cannot be changed on a task which is not cyclic
+    NotCyclic,
+    /// No mode with the given name
+    UnknownMode,
+    /// Schedule table holds a task without process runnable or with an execution cycle,
+    /// including a cycle given by a mode
+    InvalidScheduleTable,
+}
+
+impl fmt::Display for SchedulerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedulerError::Duplicate { existing } => write!(
+                f,
+                "either same name, init runnable or process runnable than already added task {}",
+                existing
+            ),
+            SchedulerError::Full => write!(f, "task list already full"),
+            SchedulerError::InvalidCycle => write!(f, "execution cycle must be greater than 0"),
+            SchedulerError::UnknownTask => write!(f, "no task with such name"),
+            SchedulerError::NotCyclic => write!(f, "task is not cyclic"),
+            SchedulerError::UnknownMode => write!(f, "no mode with such name"),
+            SchedulerError::InvalidScheduleTable => write!(
+                f,
+                "schedule table tasks require a process runnable and no execution cycle"
+            ),
+        }
+    }
+}
+
+/// Action taken by the scheduler when a fallible process runnable returns an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ErrorPolicy {
+    /// Discard the error and keep scheduling the task
+    #[default]
+    Ignore,
+    /// Execute the init runnable again before the next execution
+    Restart,
+    /// Stop scheduling the task until it is resumed
+    Suspend,
+    /// Forward the error to the registered fault hook
+    Escalate,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Process {
+    Infallible(ProcessRunnable),
+    Fallible(FallibleProcessRunnable),
+}
+
+/// Task state which is updated at runtime, the cycle monitor is only touched by the super loop
+/// along with the mode membership, whereas event monitor and suspension can be accessed
+/// from any execution context
 #[derive(Debug)]
 struct TaskCtrlBlock {
-    cycle_monitor: u32,
-    event_monitor: u32,
+    cycle_monitor: Cell<u32>,
+    run_count: Cell<u32>,
+    last_run: Cell<Option<u32>>,
+    execution_time: Cell<u32>,
+    max_execution_time: Cell<u32>,
+    deadline_misses: Cell<u32>,
+    event_monitor: Shared<EventMask>,
+    suspended: AtomicBool,
+    active: Cell<bool>,
+    /// Set by an expiry point of the schedule table until the task is dispatched
+    expired: Cell<bool>,
+    /// Execution cycle changed at runtime, which prevails over the cycles of the modes
+    cycle_override: Cell<Option<u32>>,
+}
+
+/// Read-only snapshot of a task configuration and runtime state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TaskInfo {
+    pub name: TaskName,
+    pub period: Option<u32>,
+    pub offset: Option<u32>,
+    /// Tick of the next cyclic execution, if the task is cyclic
+    pub next_deadline: Option<u32>,
+    pub pending_events: EventMask,
+    /// Number of executions of the process runnable, wrapping on overflow
+    pub run_count: u32,
+    /// Tick of the last execution of the process runnable, if any
+    pub last_run: Option<u32>,
+    /// Duration of the last execution in microseconds. Execution times are only measured
+    /// with the `telemetry`, `shell` or `std` features, they are zero otherwise
+    pub execution_time: u32,
+    /// Longest execution in microseconds
+    pub max_execution_time: u32,
+    /// Cyclic executions dispatched a full cycle or more after their deadline
+    pub deadline_misses: u32,
+    pub suspended: bool,
+    /// Whether the task belongs to the current mode, always true if no modes are registered
+    pub active: bool,
+}
+
+/// Peak usage of the main stack along with the task during which it was reached,
+/// no task means it was reached before launching or while idle
+#[cfg(feature = "stack-monitor")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct StackHighWaterMark {
+    /// Bytes used from the top of the stack
+    pub used: u32,
+    /// Bytes of the stack region
+    pub size: u32,
+    pub task: Option<TaskName>,
 }
 
 #[derive(Debug)]
 pub struct Task {
     name: TaskName,
     init_runnable: Option<InitRunnable>,
-    process_runnable: Option<ProcessRunnable>,
-    execution_cycle: Option<u32>,
-    execution_offset: Option<u32>,
+    process_runnable: Option<Process>,
+    /// Execution cycle can be changed at runtime, see [`Scheduler::set_task_period`]
+    /// and [`Scheduler::switch_mode`]
+    execution_cycle: Cell<Option<u32>>,
+    /// Execution cycle the task was created with, restored by modes not overriding it
+    default_cycle: Option<u32>,
+    /// Execution offset is assigned at launch if not specified and offset assignment is enabled
+    execution_offset: Cell<Option<u32>>,
+    error_policy: ErrorPolicy,
+    /// Priority considered by the dispatch policy, the higher the more urgent
+    priority: u8,
+    /// Execution budget in milliseconds
+    #[cfg(feature = "budget")]
+    budget: Option<u32>,
     tcb: TaskCtrlBlock,
 }
 
@@ -51,19 +217,95 @@ impl Task {
         execution_cycle: Option<u32>,
         execution_offset: Option<u32>,
     ) -> Task {
-        Task {
+        let process_runnable = match process_runnable {
+            Some(process_runnable) => Some(Process::Infallible(process_runnable)),
+            None => None,
+        };
+        Task::with_process(
             name,
             init_runnable,
             process_runnable,
             execution_cycle,
             execution_offset,
+            ErrorPolicy::Ignore,
+        )
+    }
+
+    /// Creates a task whose process runnable reports errors, which are
+    /// handled by the scheduler according to `error_policy`
+    pub const fn new_fallible(
+        name: TaskName,
+        init_runnable: Option<InitRunnable>,
+        process_runnable: Option<FallibleProcessRunnable>,
+        execution_cycle: Option<u32>,
+        execution_offset: Option<u32>,
+        error_policy: ErrorPolicy,
+    ) -> Task {
+        let process_runnable = match process_runnable {
+            Some(process_runnable) => Some(Process::Fallible(process_runnable)),
+            None => None,
+        };
+        Task::with_process(
+            name,
+            init_runnable,
+            process_runnable,
+            execution_cycle,
+            execution_offset,
+            error_policy,
+        )
+    }
+
+    const fn with_process(
+        name: TaskName,
+        init_runnable: Option<InitRunnable>,
+        process_runnable: Option<Process>,
+        execution_cycle: Option<u32>,
+        execution_offset: Option<u32>,
+        error_policy: ErrorPolicy,
+    ) -> Task {
+        Task {
+            name,
+            init_runnable,
+            process_runnable,
+            execution_cycle: Cell::new(execution_cycle),
+            default_cycle: execution_cycle,
+            execution_offset: Cell::new(execution_offset),
+            error_policy,
+            priority: 0,
+            #[cfg(feature = "budget")]
+            budget: None,
             tcb: TaskCtrlBlock {
-                cycle_monitor: 0,
-                event_monitor: 0,
+                cycle_monitor: Cell::new(0),
+                run_count: Cell::new(0),
+                last_run: Cell::new(None),
+                execution_time: Cell::new(0),
+                max_execution_time: Cell::new(0),
+                deadline_misses: Cell::new(0),
+                event_monitor: Shared::new(0),
+                suspended: AtomicBool::new(false),
+                active: Cell::new(true),
+                expired: Cell::new(false),
+                cycle_override: Cell::new(None),
             },
         }
     }
 
+    /// Sets the priority of the task, only considered by priority based dispatch policies,
+    /// e.g. [`dispatch::FixedPriority`]
+    pub const fn with_priority(mut self, priority: u8) -> Task {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the execution budget in milliseconds of the process runnable, the registered
+    /// overrun hook is invoked if an execution exceeds it, with the 1 ms resolution of
+    /// the tick
+    #[cfg(feature = "budget")]
+    pub const fn with_budget(mut self, budget: u32) -> Task {
+        self.budget = Some(budget);
+        self
+    }
+
     pub fn has_duplicates_of(&self, other: &Self) -> bool {
         self.name == other.name
             || self.has_same_init_runnable_as(other)
@@ -74,153 +316,712 @@ impl Task {
         if let (Some(init_runnable), Some(other_init_runnable)) =
             (self.init_runnable, other.init_runnable)
         {
-            init_runnable == other_init_runnable
+            core::ptr::fn_addr_eq(init_runnable, other_init_runnable)
         } else {
             false
         }
     }
 
     fn has_same_process_runnable_as(&self, other: &Self) -> bool {
-        if let (Some(process_runnable), Some(other_process_runnable)) =
-            (self.process_runnable, other.process_runnable)
-        {
-            process_runnable == other_process_runnable
-        } else {
-            false
+        match (self.process_runnable, other.process_runnable) {
+            (
+                Some(Process::Infallible(process_runnable)),
+                Some(Process::Infallible(other_process_runnable)),
+            ) => core::ptr::fn_addr_eq(process_runnable, other_process_runnable),
+            (
+                Some(Process::Fallible(process_runnable)),
+                Some(Process::Fallible(other_process_runnable)),
+            ) => core::ptr::fn_addr_eq(process_runnable, other_process_runnable),
+            _ => false,
+        }
+    }
+
+    /// Tick of the next cyclic execution, if the task is cyclic
+    fn next_deadline(&self) -> Option<u32> {
+        match (self.process_runnable, self.execution_cycle.get()) {
+            (Some(_), Some(_)) => Some(self.tcb.cycle_monitor.get()),
+            _ => None,
+        }
+    }
+
+    fn info(&self) -> TaskInfo {
+        TaskInfo {
+            name: self.name,
+            period: self.execution_cycle.get(),
+            offset: self.execution_offset.get(),
+            next_deadline: self.next_deadline(),
+            pending_events: self.tcb.event_monitor.lock(|event_monitor| *event_monitor),
+            run_count: self.tcb.run_count.get(),
+            last_run: self.tcb.last_run.get(),
+            execution_time: self.tcb.execution_time.get(),
+            max_execution_time: self.tcb.max_execution_time.get(),
+            deadline_misses: self.tcb.deadline_misses.get(),
+            suspended: self.is_suspended(),
+            active: self.tcb.active.get(),
+        }
+    }
+
+    #[inline]
+    fn is_suspended(&self) -> bool {
+        self.tcb.suspended.load(Ordering::Relaxed)
+    }
+
+    fn process(&self, event_mask: EventMask, fault_hook: Option<FaultHook>) {
+        let result = match self.process_runnable {
+            Some(Process::Infallible(process_runnable)) => {
+                process_runnable(event_mask);
+                Ok(())
+            }
+            Some(Process::Fallible(process_runnable)) => process_runnable(event_mask),
+            None => Ok(()),
+        };
+
+        if let Err(error) = result {
+            log_error!(
+                "Task {} failed with error {:?}, applying policy {:?}",
+                self.name,
+                error,
+                self.error_policy
+            );
+            match self.error_policy {
+                ErrorPolicy::Ignore => (),
+                ErrorPolicy::Restart => {
+                    if let Some(init_runnable) = self.init_runnable {
+                        init_runnable();
+                    }
+                }
+                ErrorPolicy::Suspend => self.tcb.suspended.store(true, Ordering::Relaxed),
+                ErrorPolicy::Escalate => match fault_hook {
+                    Some(fault_hook) => fault_hook(self.name, error),
+                    None => panic!(
+                        "Task {} escalated error {:?} but no fault hook is registered",
+                        self.name, error
+                    ),
+                },
+            }
         }
     }
 }
 
 pub struct Scheduler<const TASK_COUNT: usize, const CORE_FREQ: u32> {
+    dispatch_policy: &'static dyn DispatchPolicy,
     idle_runnable: Option<IdleRunnable>,
+    fault_hook: Option<FaultHook>,
+    trace_sink: Option<&'static dyn TraceSink>,
+    busy_time: Cell<u32>,
+    offset_assignment: bool,
+    #[cfg(feature = "stack-monitor")]
+    stack_high_water_mark: Cell<Option<StackHighWaterMark>>,
+    modes: &'static [Mode],
+    /// Index of the current mode and of the one requested to be switched to
+    current_mode: Cell<Option<usize>>,
+    pending_mode: Shared<Option<usize>>,
+    schedule_table: Option<&'static ScheduleTable>,
+    /// Tick at which the current period of the schedule table started and next expiry point
+    table_start: Cell<u32>,
+    expiry_point: Cell<usize>,
     task_list: TaskList<TASK_COUNT>,
 }
 
+impl<const TASK_COUNT: usize, const CORE_FREQ: u32> Default for Scheduler<TASK_COUNT, CORE_FREQ> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<const TASK_COUNT: usize, const CORE_FREQ: u32> Scheduler<TASK_COUNT, CORE_FREQ> {
+    /// Creates a scheduler dispatching the tasks in list order
     pub const fn new() -> Scheduler<TASK_COUNT, CORE_FREQ> {
+        Scheduler::with_dispatch_policy(&ListOrder)
+    }
+
+    /// Creates a scheduler dispatching the tasks according to `dispatch_policy`
+    pub const fn with_dispatch_policy(
+        dispatch_policy: &'static dyn DispatchPolicy,
+    ) -> Scheduler<TASK_COUNT, CORE_FREQ> {
         Scheduler {
+            dispatch_policy,
             idle_runnable: None,
+            fault_hook: None,
+            trace_sink: None,
+            busy_time: Cell::new(0),
+            offset_assignment: false,
+            #[cfg(feature = "stack-monitor")]
+            stack_high_water_mark: Cell::new(None),
+            modes: &[],
+            current_mode: Cell::new(None),
+            pending_mode: Shared::new(None),
+            schedule_table: None,
+            table_start: Cell::new(0),
+            expiry_point: Cell::new(0),
             task_list: TaskList::new(),
         }
     }
 
+    /// Adds a task to the scheduler, panics upon any configuration error.
+    /// See [`Scheduler::try_add_task`] for a non-panicking alternative
     pub fn add_task(&mut self, task: Task) {
-        #[cfg(debug_assertions)]
-        log!(
+        let name = task.name;
+        if let Err(error) = self.try_add_task(task) {
+            panic!("Task {} cannot be added, {}", name, error);
+        }
+    }
+
+    pub fn try_add_task(&mut self, task: Task) -> Result<(), SchedulerError> {
+        log_info!(
             "Adding task {} to scheduler: \n \
-              - init runnable: {:?}\n \
-              - process runnable: {:?}\n \
+              - init runnable: {}\n \
+              - process runnable: {}\n \
               - execution cycle: {:?}\n \
-              - execution offset: {:?}",
+              - execution offset: {:?}\n \
+              - error policy: {:?}",
             task.name,
-            task.init_runnable,
-            task.process_runnable,
-            task.execution_cycle,
-            task.execution_offset
+            task.init_runnable.is_some(),
+            task.process_runnable.is_some(),
+            task.execution_cycle.get(),
+            task.execution_offset.get(),
+            task.error_policy
         );
-        self.check_if_task_has_duplicates(&task);
-        if let Err(task) = self.task_list.push(task) {
-            panic!("Task {} cannot be added, task list already full", task.name);
+        let name = task.name;
+        self.check_task(task).inspect_err(|error| {
+            log_error!("Task {} cannot be added, {:?}", name, error);
+        })
+    }
+
+    fn check_task(&mut self, task: Task) -> Result<(), SchedulerError> {
+        if task.execution_cycle.get() == Some(0) {
+            return Err(SchedulerError::InvalidCycle);
         }
+        self.check_if_task_has_duplicates(&task)?;
+        self.task_list.push(task).map_err(|_| SchedulerError::Full)
     }
 
-    pub fn launch(&mut self) {
+    pub fn launch(&self) {
         let systick = SysTick::bind_with_core_and_take(CORE_FREQ).unwrap();
         systick.launch();
 
-        for task in self.task_list.iter_mut() {
-            #[cfg(debug_assertions)]
-            log!("Launching task {}", task.name);
+        #[cfg(feature = "stack-monitor")]
+        port::stack::paint();
+        self.monitor_stack(None);
+
+        self.init_tasks(systick.get());
+
+        // Main endless super loop
+        loop {
+            let task_execution = self.process_tasks(systick.get());
+            // Execute idle runnable if registered and there was no execution
+            if let Some(idle_runnable) = self.idle_runnable {
+                if !task_execution {
+                    self.trace(TraceEvent::IdleEntered);
+                    idle_runnable();
+                    self.monitor_stack(None);
+                    self.trace(TraceEvent::IdleLeft);
+                }
+            }
+        }
+    }
+
+    fn init_tasks(&self, tick: u32) {
+        if self.offset_assignment {
+            self.assign_offsets();
+        }
+        self.table_start.set(tick);
+        self.expiry_point.set(0);
+        for task in self.task_list.iter() {
+            log_info!("Launching task {}", task.name);
 
             // Execute init_runnable if any
             if let Some(init_runnable) = task.init_runnable {
                 init_runnable();
+                self.monitor_stack(Some(task.name));
             }
 
             // Update cycle monitor if any process_runnable function and exeuction_cycle configured
-            if let (Some(_), Some(execution_cycle)) = (task.process_runnable, task.execution_cycle)
+            if let (Some(_), Some(execution_cycle)) =
+                (task.process_runnable, task.execution_cycle.get())
             {
-                task.tcb.cycle_monitor =
-                    systick.get() + execution_cycle + task.execution_offset.unwrap_or(0);
+                task.tcb
+                    .cycle_monitor
+                    .set(tick + execution_cycle + task.execution_offset.get().unwrap_or(0));
             }
         }
+    }
 
-        // Main endless super loop
+    /// Assigns an offset to the cyclic tasks without one, from the shortest period to the
+    /// longest so that the most frequent tasks are spread first
+    fn assign_offsets(&self) {
+        let is_cyclic =
+            |task: &&Task| task.process_runnable.is_some() && task.execution_cycle.get().is_some();
+        while let Some(task) = self
+            .task_list
+            .iter()
+            .filter(is_cyclic)
+            .filter(|task| task.execution_offset.get().is_none())
+            .min_by_key(|task| task.execution_cycle.get())
+        {
+            let placed = self.task_list.iter().filter(is_cyclic).filter_map(|task| {
+                let offset = task.execution_offset.get()?;
+                Some((task.execution_cycle.get()?, offset))
+            });
+            let period = task.execution_cycle.get().unwrap_or(1);
+            let offset = offsets::best_offset(period, placed);
+            log_info!("Assigning offset {} to task {}", offset, task.name);
+            task.execution_offset.set(Some(offset));
+        }
+    }
+
+    /// Executes a single pass over the task list, returns whether any task was executed
+    fn process_tasks(&self, tick: u32) -> bool {
+        self.apply_pending_mode(tick);
+        self.process_schedule_table(tick);
+        if self.dispatch_policy.keeps_list_order() {
+            return self.process_in_order(self.task_list.iter(), tick);
+        }
+        let mut candidates: Vec<DispatchCandidate, TASK_COUNT> = self
+            .task_list
+            .iter()
+            .enumerate()
+            .map(|(index, task)| DispatchCandidate {
+                index,
+                name: task.name,
+                priority: task.priority,
+                deadline: task.next_deadline(),
+            })
+            .collect();
+        self.dispatch_policy.order(&mut candidates);
+        self.process_in_order(
+            candidates
+                .iter()
+                .map(|candidate| &self.task_list[candidate.index]),
+            tick,
+        )
+    }
+
+    /// Processes the tasks in the order of the pass, until the first dispatch if the policy
+    /// restarts a pass after each one
+    fn process_in_order<'a>(&'a self, tasks: impl Iterator<Item = &'a Task>, tick: u32) -> bool {
+        let mut task_execution = false;
+        for task in tasks {
+            if self.process_task(task, tick) {
+                task_execution = true;
+                if self.dispatch_policy.restart_after_dispatch() {
+                    break;
+                }
+            }
+        }
+        task_execution
+    }
+
+    /// Dispatches the task if it is due or has pending events, returns whether it was executed
+    fn process_task(&self, task: &Task, tick: u32) -> bool {
+        // Suspended and inactive tasks keep their pending events until resumed or activated
+        if task.is_suspended() || !task.tcb.active.get() || task.process_runnable.is_none() {
+            return false;
+        }
+        let mut task_execution = false;
+        let mut cyclic_execution = task.tcb.expired.take();
+        // Update cycle monitor with new absolut time
+        if let Some(execution_cycle) = task.execution_cycle.get() {
+            let deadline = task.tcb.cycle_monitor.get();
+            if tick >= deadline {
+                // At least one cyclic execution got lost
+                if tick - deadline >= execution_cycle {
+                    let deadline_misses = &task.tcb.deadline_misses;
+                    deadline_misses.set(deadline_misses.get().wrapping_add(1));
+                }
+                task.tcb.cycle_monitor.set(tick + execution_cycle);
+                cyclic_execution = true;
+            }
+        }
+        // Execute process runnable if any event set
+        let event_mask = task.tcb.event_monitor.lock(core::mem::take);
+        if event_mask != 0 {
+            self.dispatch(task, Cause::Events(event_mask), tick);
+            task_execution = true;
+        }
+        // Execute process runnable if cycle period elapsed and task was not suspended meanwhile
+        if cyclic_execution && !task.is_suspended() {
+            self.dispatch(task, Cause::Cycle, tick);
+            task_execution = true;
+        }
+        task_execution
+    }
+
+    /// Switches to the requested mode, if any, before the tasks are processed so that
+    /// no pass is executed with a partially applied mode
+    fn apply_pending_mode(&self, tick: u32) {
+        let Some(index) = self.pending_mode.lock(Option::take) else {
+            return;
+        };
+        let mode = &self.modes[index];
+        log_info!("Switching to mode {}", mode.name);
+        if let Some(exit_hook) = self
+            .current_mode
+            .get()
+            .and_then(|i| self.modes[i].exit_hook)
+        {
+            exit_hook();
+        }
+        for task in self.task_list.iter() {
+            let mode_task = mode.task(task.name);
+            task.tcb.active.set(mode_task.is_some());
+            let execution_cycle = mode_task
+                .and_then(|mode_task| mode_task.execution_cycle)
+                .or(task.default_cycle)
+                .map(|cycle| task.tcb.cycle_override.get().unwrap_or(cycle));
+            task.execution_cycle.set(execution_cycle);
+            // Cycles restart from the switch, keeping the offsets between tasks
+            if let (Some(_), Some(execution_cycle)) = (task.process_runnable, execution_cycle) {
+                task.tcb
+                    .cycle_monitor
+                    .set(tick + execution_cycle + task.execution_offset.get().unwrap_or(0));
+            }
+        }
+        self.current_mode.set(Some(index));
+        if let Some(enter_hook) = mode.enter_hook {
+            enter_hook();
+        }
+    }
+
+    /// Walks the schedule table up to `tick`, flagging the tasks of each elapsed expiry point
+    /// so that they are dispatched by the current pass
+    fn process_schedule_table(&self, tick: u32) {
+        let Some(schedule_table) = self.schedule_table else {
+            return;
+        };
         loop {
-            let mut task_execution = false;
-            for task in self.task_list.iter_mut() {
-                let mut cyclic_execution = false;
-                if let Some(process_runnable) = task.process_runnable {
-                    // Update cycle monitor with new absolut time
-                    if let Some(execution_cycle) = task.execution_cycle {
-                        if systick.get() >= task.tcb.cycle_monitor {
-                            task.tcb.cycle_monitor = systick.get() + execution_cycle;
-                            cyclic_execution = true;
+            let table_start = self.table_start.get();
+            match schedule_table.expiry_points.get(self.expiry_point.get()) {
+                Some(expiry_point) if tick >= table_start + expiry_point.offset => {
+                    for name in expiry_point.tasks {
+                        let Some(task) = self.find_task(name) else {
+                            continue;
+                        };
+                        if task.is_suspended() || !task.tcb.active.get() {
+                            continue;
+                        }
+                        // Previous expiry of the task not dispatched yet
+                        if task.tcb.expired.replace(true) {
+                            let deadline_misses = &task.tcb.deadline_misses;
+                            deadline_misses.set(deadline_misses.get().wrapping_add(1));
                         }
                     }
-                    // Execute process runnable if any event set
-                    if task.tcb.event_monitor != 0 {
-                        let mut event_mask = 0;
-                        critical_section(|_| {
-                            event_mask = task.tcb.event_monitor;
-                            task.tcb.event_monitor = 0;
-                        });
-                        process_runnable(event_mask);
-                        task_execution = true;
-                    }
-                    // Execute process runnable if cycle period elapsed
-                    if cyclic_execution {
-                        process_runnable(0);
-                        task_execution = true;
-                    }
+                    self.expiry_point.set(self.expiry_point.get() + 1);
                 }
-            }
-            // Execute idle runnable if registered and there was no execution
-            if let Some(idle_runnable) = self.idle_runnable {
-                if !task_execution {
-                    idle_runnable();
+                None if tick >= table_start + schedule_table.duration => {
+                    self.table_start.set(table_start + schedule_table.duration);
+                    self.expiry_point.set(0);
                 }
+                _ => break,
+            }
+        }
+    }
+
+    fn dispatch(&self, task: &Task, cause: Cause, tick: u32) {
+        log_trace!("Dispatching task {} ({:?})", task.name, cause);
+        self.trace(TraceEvent::TaskDispatched {
+            task: task.name,
+            cause,
+        });
+        let event_mask = match cause {
+            Cause::Cycle => 0,
+            Cause::Events(event_mask) => event_mask,
+        };
+        task.tcb
+            .run_count
+            .set(task.tcb.run_count.get().wrapping_add(1));
+        task.tcb.last_run.set(Some(tick));
+        #[cfg(any(feature = "telemetry", feature = "shell", feature = "std"))]
+        let start = port::now_us();
+        #[cfg(feature = "budget")]
+        if let Some(budget) = task.budget {
+            budget::arm(task.name, budget);
+        }
+        task.process(event_mask, self.fault_hook);
+        #[cfg(feature = "budget")]
+        budget::disarm();
+        #[cfg(any(feature = "telemetry", feature = "shell", feature = "std"))]
+        let execution_time = port::now_us().wrapping_sub(start);
+        self.monitor_stack(Some(task.name));
+        #[cfg(any(feature = "telemetry", feature = "shell", feature = "std"))]
+        self.record_execution_time(task, execution_time);
+        self.trace(TraceEvent::TaskFinished { task: task.name });
+    }
+
+    /// Execution times are only measured for the features reporting them, the time base
+    /// being read within a critical section
+    #[cfg(any(feature = "telemetry", feature = "shell", feature = "std"))]
+    fn record_execution_time(&self, task: &Task, execution_time: u32) {
+        task.tcb.execution_time.set(execution_time);
+        if execution_time > task.tcb.max_execution_time.get() {
+            task.tcb.max_execution_time.set(execution_time);
+        }
+        self.busy_time
+            .set(self.busy_time.get().wrapping_add(execution_time));
+    }
+
+    /// Updates the stack high-water mark, attributing a new peak to `task`
+    #[cfg(feature = "stack-monitor")]
+    fn monitor_stack(&self, task: Option<TaskName>) {
+        if let Some(used) = port::stack::peak() {
+            let current = self.stack_high_water_mark.get();
+            if current.is_none_or(|current| used > current.used) {
+                self.stack_high_water_mark.set(Some(StackHighWaterMark {
+                    used,
+                    size: port::stack::size(),
+                    task,
+                }));
             }
         }
     }
 
+    #[cfg(not(feature = "stack-monitor"))]
+    #[inline(always)]
+    fn monitor_stack(&self, _task: Option<TaskName>) {}
+
+    /// Peak stack usage since launch, if the stack was painted
+    #[cfg(feature = "stack-monitor")]
+    #[inline]
+    pub fn stack_high_water_mark(&self) -> Option<StackHighWaterMark> {
+        self.stack_high_water_mark.get()
+    }
+
+    #[inline]
+    fn trace(&self, event: TraceEvent) {
+        if let Some(trace_sink) = self.trace_sink {
+            trace_sink.trace(event);
+        }
+    }
+
     #[inline]
     pub fn register_idle_runnable(&mut self, idle: fn()) {
         self.idle_runnable = Some(idle);
     }
 
     #[inline]
-    pub fn set_task_event(&mut self, name: &str, event: u32) {
-        if let Some(task) = self.task_list.iter_mut().find(|task| task.name == name) {
-            critical_section(|_| task.tcb.event_monitor |= event);
-        }
+    pub fn register_fault_hook(&mut self, fault_hook: FaultHook) {
+        self.fault_hook = Some(fault_hook);
     }
 
+    /// Registers the hook invoked from the tick interrupt when a task exceeds its budget
+    #[cfg(feature = "budget")]
     #[inline]
-    pub fn clear_task_event(&mut self, name: &str, event: u32) {
-        if let Some(task) = self.task_list.iter_mut().find(|task| task.name == name) {
-            critical_section(|_| task.tcb.event_monitor &= !event);
+    pub fn register_overrun_hook(&mut self, overrun_hook: OverrunHook) {
+        budget::register(overrun_hook);
+    }
+
+    /// Registers the modes of the application, the first one is entered upon the first pass.
+    /// Tasks are required to be added beforehand, since the modes are checked against them
+    pub fn register_modes(&mut self, modes: &'static [Mode]) -> Result<(), SchedulerError> {
+        for (index, mode) in modes.iter().enumerate() {
+            if let Some(existing) = modes[..index].iter().find(|other| other.name == mode.name) {
+                return Err(SchedulerError::Duplicate {
+                    existing: existing.name,
+                });
+            }
+            if let Some(mode_task) = mode.tasks.iter().find(|t| self.find_task(t.name).is_none()) {
+                log_error!("Mode {} holds unknown task {}", mode.name, mode_task.name);
+                return Err(SchedulerError::UnknownTask);
+            }
+            if mode.tasks.iter().any(|t| t.execution_cycle == Some(0)) {
+                return Err(SchedulerError::InvalidCycle);
+            }
+        }
+        if let Some(schedule_table) = self.schedule_table {
+            Self::check_table_cycles(modes, schedule_table)?;
         }
+        self.modes = modes;
+        self.current_mode.set(None);
+        self.pending_mode
+            .lock(|pending_mode| *pending_mode = (!modes.is_empty()).then_some(0));
+        Ok(())
     }
 
+    /// Requests switching to another mode, which is applied at the beginning of the next
+    /// pass over the tasks. It can be requested from any execution context
+    pub fn switch_mode(&self, name: &str) -> Result<(), SchedulerError> {
+        let index = self
+            .modes
+            .iter()
+            .position(|mode| mode.name == name)
+            .ok_or(SchedulerError::UnknownMode)?;
+        self.pending_mode
+            .lock(|pending_mode| *pending_mode = Some(index));
+        Ok(())
+    }
+
+    /// Name of the current mode, a requested switch is only reflected once applied
     #[inline]
-    pub fn get_task_event(&mut self, name: &str) -> Option<u32> {
-        if let Some(task) = self.task_list.iter().find(|task| task.name == name) {
-            critical_section(|_| Some(task.tcb.event_monitor))
-        } else {
-            None
+    pub fn current_mode(&self) -> Option<ModeName> {
+        self.current_mode.get().map(|index| self.modes[index].name)
+    }
+
+    /// Registers the schedule table which dispatches its tasks at the expiry points, it starts
+    /// when launching. Tasks are required to be added beforehand, the tasks of the table need a
+    /// process runnable and no execution cycle. See [`ScheduleTable`] for the checked layout
+    pub fn register_schedule_table(
+        &mut self,
+        schedule_table: &'static ScheduleTable,
+    ) -> Result<(), SchedulerError> {
+        for name in schedule_table.tasks() {
+            let task = self.find_task(name).ok_or_else(|| {
+                log_error!("Schedule table holds unknown task {}", name);
+                SchedulerError::UnknownTask
+            })?;
+            if task.process_runnable.is_none() || task.default_cycle.is_some() {
+                return Err(SchedulerError::InvalidScheduleTable);
+            }
         }
+        Self::check_table_cycles(self.modes, schedule_table)?;
+        self.schedule_table = Some(schedule_table);
+        Ok(())
     }
 
-    fn check_if_task_has_duplicates(&self, task: &Task) {
-        for added_task in self.task_list.iter() {
-            if task.has_duplicates_of(added_task) {
-                panic!(
-                    "Task {} has either same name or init runnable: {:?} \
-                    or process runnable: {:?} than an already added task {}",
-                    task.name, task.init_runnable, task.process_runnable, added_task.name
+    /// Tasks of the schedule table cannot be given an execution cycle by a mode, they would
+    /// be dispatched both at the expiry points and cyclically
+    fn check_table_cycles(
+        modes: &[Mode],
+        schedule_table: &ScheduleTable,
+    ) -> Result<(), SchedulerError> {
+        for mode in modes {
+            if let Some(mode_task) = mode.tasks.iter().find(|mode_task| {
+                mode_task.execution_cycle.is_some()
+                    && schedule_table.tasks().any(|name| name == mode_task.name)
+            }) {
+                log_error!(
+                    "Mode {} gives an execution cycle to task {} of the schedule table",
+                    mode.name,
+                    mode_task.name
                 );
+                return Err(SchedulerError::InvalidScheduleTable);
+            }
+        }
+        Ok(())
+    }
+
+    /// Enables the assignment at launch of the offsets of the cyclic tasks which do not
+    /// specify one, minimising the number of tasks due on the same tick
+    #[inline]
+    pub fn enable_offset_assignment(&mut self) {
+        self.offset_assignment = true;
+    }
+
+    /// Registers the sink which receives the trace points of the scheduler activity
+    #[inline]
+    pub fn register_trace_sink(&mut self, trace_sink: &'static dyn TraceSink) {
+        self.trace_sink = Some(trace_sink);
+    }
+
+    #[inline]
+    pub fn suspend_task(&self, name: &str) {
+        if let Some(task) = self.find_task(name) {
+            task.tcb.suspended.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[inline]
+    pub fn resume_task(&self, name: &str) {
+        if let Some(task) = self.find_task(name) {
+            task.tcb.suspended.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// Changes the execution cycle of a cyclic task, the new cycle applies from its next execution.
+    /// It is kept across mode switches, in place of the cycle set by the modes
+    pub fn set_task_period(&self, name: &str, execution_cycle: u32) -> Result<(), SchedulerError> {
+        let task = self.find_task(name).ok_or(SchedulerError::UnknownTask)?;
+        if execution_cycle == 0 {
+            return Err(SchedulerError::InvalidCycle);
+        }
+        match (task.process_runnable, task.execution_cycle.get()) {
+            (Some(_), Some(_)) => {
+                task.execution_cycle.set(Some(execution_cycle));
+                task.tcb.cycle_override.set(Some(execution_cycle));
+                Ok(())
             }
+            _ => Err(SchedulerError::NotCyclic),
+        }
+    }
+
+    #[inline]
+    pub fn set_task_event(&self, name: &str, event: EventMask) {
+        if let Some(task) = self.find_task(name) {
+            task.tcb
+                .event_monitor
+                .lock(|event_monitor| *event_monitor |= event);
+            self.trace(TraceEvent::EventSet {
+                task: task.name,
+                event,
+            });
+        }
+    }
+
+    #[inline]
+    pub fn clear_task_event(&self, name: &str, event: EventMask) {
+        if let Some(task) = self.find_task(name) {
+            task.tcb
+                .event_monitor
+                .lock(|event_monitor| *event_monitor &= !event);
+            self.trace(TraceEvent::EventCleared {
+                task: task.name,
+                event,
+            });
+        }
+    }
+
+    /// Sets typed events on a task, either a single event or a set of them
+    #[inline]
+    pub fn set_task_event_typed<E: Event>(&self, name: &str, events: impl Into<EventSet<E>>) {
+        self.set_task_event(name, events.into().mask());
+    }
+
+    /// Clears typed events of a task, either a single event or a set of them
+    #[inline]
+    pub fn clear_task_event_typed<E: Event>(&self, name: &str, events: impl Into<EventSet<E>>) {
+        self.clear_task_event(name, events.into().mask());
+    }
+
+    #[inline]
+    pub fn get_task_event(&self, name: &str) -> Option<u32> {
+        self.find_task(name)
+            .map(|task| task.tcb.event_monitor.lock(|event_monitor| *event_monitor))
+    }
+
+    /// Accumulated execution time of all tasks in microseconds, wrapping on overflow.
+    /// CPU load is derived from its increase over a time window, see [`TaskInfo::execution_time`]
+    /// for the features measuring it
+    #[inline]
+    pub fn busy_time(&self) -> u32 {
+        self.busy_time.get()
+    }
+
+    /// Iterates over the tasks in the order they were added, runtime state is
+    /// updated by the super loop, e.g. run count and next deadline
+    pub fn tasks(&self) -> impl Iterator<Item = TaskInfo> + '_ {
+        self.task_list.iter().map(Task::info)
+    }
+
+    /// Whether a task of this name was added
+    #[inline]
+    pub fn has_task(&self, name: &str) -> bool {
+        self.find_task(name).is_some()
+    }
+
+    #[inline]
+    fn find_task(&self, name: &str) -> Option<&Task> {
+        self.task_list.iter().find(|task| task.name == name)
+    }
+
+    fn check_if_task_has_duplicates(&self, task: &Task) -> Result<(), SchedulerError> {
+        match self
+            .task_list
+            .iter()
+            .find(|added_task| task.has_duplicates_of(added_task))
+        {
+            Some(added_task) => Err(SchedulerError::Duplicate {
+                existing: added_task.name,
+            }),
+            None => Ok(()),
         }
     }
 }
@@ -228,6 +1029,7 @@ impl<const TASK_COUNT: usize, const CORE_FREQ: u32> Scheduler<TASK_COUNT, CORE_F
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::AtomicU32;
 
     const DUMMY_CORE_FREQ: u32 = 100_000_000;
 
@@ -243,8 +1045,10 @@ mod tests {
 
         let task_event_mask = scheduler.get_task_event(TASK_NAME);
         assert_eq!(task_event_mask, None);
+        assert!(!scheduler.has_task(TASK_NAME));
 
         scheduler.add_task(task);
+        assert!(scheduler.has_task(TASK_NAME));
 
         let task_event_mask = scheduler.get_task_event(TASK_NAME);
         assert_eq!(task_event_mask, Some(0));
@@ -318,4 +1122,767 @@ mod tests {
         scheduler.add_task(task1);
         scheduler.add_task(task2);
     }
+
+    #[test]
+    fn fallible_task_restart_policy() {
+        const TASK_COUNT: usize = 1;
+        const TASK_NAME: &str = "Dummy task";
+        static INIT_COUNT: AtomicU32 = AtomicU32::new(0);
+        fn dummy_init_runnable() {
+            INIT_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+        fn dummy_process_runnable(_event_mask: EventMask) -> Result<(), TaskError> {
+            Err(TaskError(1))
+        }
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        let task = Task::new_fallible(
+            TASK_NAME,
+            Some(dummy_init_runnable),
+            Some(dummy_process_runnable),
+            None,
+            None,
+            ErrorPolicy::Restart,
+        );
+
+        scheduler.add_task(task);
+        scheduler.init_tasks(0);
+        assert_eq!(INIT_COUNT.load(Ordering::Relaxed), 1);
+
+        scheduler.set_task_event(TASK_NAME, 0x00000001);
+        assert!(scheduler.process_tasks(0));
+        assert_eq!(INIT_COUNT.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn fallible_task_suspend_policy() {
+        const TASK_COUNT: usize = 1;
+        const TASK_NAME: &str = "Dummy task";
+        const TASK_EVENT: EventMask = 0x00000001;
+        static PROCESS_COUNT: AtomicU32 = AtomicU32::new(0);
+        fn dummy_process_runnable(_event_mask: EventMask) -> Result<(), TaskError> {
+            PROCESS_COUNT.fetch_add(1, Ordering::Relaxed);
+            Err(TaskError(1))
+        }
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        let task = Task::new_fallible(
+            TASK_NAME,
+            None,
+            Some(dummy_process_runnable),
+            Some(10),
+            None,
+            ErrorPolicy::Suspend,
+        );
+
+        scheduler.add_task(task);
+        scheduler.init_tasks(0);
+
+        scheduler.set_task_event(TASK_NAME, TASK_EVENT);
+        assert!(scheduler.process_tasks(10));
+        assert_eq!(PROCESS_COUNT.load(Ordering::Relaxed), 1);
+
+        // Suspended task is neither executed cyclically nor upon events
+        scheduler.set_task_event(TASK_NAME, TASK_EVENT);
+        assert!(!scheduler.process_tasks(20));
+        assert_eq!(PROCESS_COUNT.load(Ordering::Relaxed), 1);
+        assert_eq!(scheduler.get_task_event(TASK_NAME), Some(TASK_EVENT));
+
+        scheduler.resume_task(TASK_NAME);
+        assert!(scheduler.process_tasks(20));
+        assert_eq!(PROCESS_COUNT.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn fallible_task_escalate_policy() {
+        const TASK_COUNT: usize = 1;
+        const TASK_NAME: &str = "Dummy task";
+        static FAULT_CODE: AtomicU32 = AtomicU32::new(0);
+        fn dummy_process_runnable(_event_mask: EventMask) -> Result<(), TaskError> {
+            Err(TaskError(42))
+        }
+        fn dummy_fault_hook(name: TaskName, error: TaskError) {
+            assert_eq!(name, TASK_NAME);
+            FAULT_CODE.store(error.0, Ordering::Relaxed);
+        }
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        let task = Task::new_fallible(
+            TASK_NAME,
+            None,
+            Some(dummy_process_runnable),
+            Some(10),
+            None,
+            ErrorPolicy::Escalate,
+        );
+
+        scheduler.add_task(task);
+        scheduler.register_fault_hook(dummy_fault_hook);
+        scheduler.init_tasks(0);
+
+        assert!(!scheduler.process_tasks(5));
+        assert!(scheduler.process_tasks(10));
+        assert_eq!(FAULT_CODE.load(Ordering::Relaxed), 42);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fallible_task_escalate_without_fault_hook() {
+        const TASK_COUNT: usize = 1;
+        fn dummy_process_runnable(_event_mask: EventMask) -> Result<(), TaskError> {
+            Err(TaskError(1))
+        }
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        let task = Task::new_fallible(
+            "Dummy task",
+            None,
+            Some(dummy_process_runnable),
+            Some(10),
+            None,
+            ErrorPolicy::Escalate,
+        );
+
+        scheduler.add_task(task);
+        scheduler.init_tasks(0);
+        scheduler.process_tasks(10);
+    }
+
+    #[test]
+    fn try_add_task_errors() {
+        const TASK_COUNT: usize = 2;
+        fn dummy_process_runnable(_event_mask: EventMask) {}
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        let task1 = Task::new(
+            "Dummy task 1",
+            None,
+            Some(dummy_process_runnable),
+            None,
+            None,
+        );
+        let task2 = Task::new(
+            "Dummy task 2",
+            None,
+            Some(dummy_process_runnable),
+            None,
+            None,
+        );
+        let task3 = Task::new("Dummy task 3", None, None, Some(0), None);
+        let task4 = Task::new("Dummy task 4", None, None, None, None);
+        let task5 = Task::new("Dummy task 5", None, None, None, None);
+
+        assert_eq!(scheduler.try_add_task(task1), Ok(()));
+        assert_eq!(
+            scheduler.try_add_task(task2),
+            Err(SchedulerError::Duplicate {
+                existing: "Dummy task 1"
+            })
+        );
+        assert_eq!(
+            scheduler.try_add_task(task3),
+            Err(SchedulerError::InvalidCycle)
+        );
+        assert_eq!(scheduler.try_add_task(task4), Ok(()));
+        assert_eq!(scheduler.try_add_task(task5), Err(SchedulerError::Full));
+    }
+
+    #[test]
+    fn task_introspection() {
+        const TASK_COUNT: usize = 2;
+        fn dummy_process_runnable(_event_mask: EventMask) {
+            port::advance_us(300);
+        }
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new(
+            "Cyclic task",
+            None,
+            Some(dummy_process_runnable),
+            Some(10),
+            Some(5),
+        ));
+        scheduler.add_task(Task::new("Idle task", None, None, None, None));
+        scheduler.init_tasks(0);
+        scheduler.set_task_event("Idle task", 0x00000004);
+        scheduler.suspend_task("Idle task");
+
+        assert!(scheduler.process_tasks(15));
+        let mut tasks = scheduler.tasks();
+        assert_eq!(
+            tasks.next(),
+            Some(TaskInfo {
+                name: "Cyclic task",
+                period: Some(10),
+                offset: Some(5),
+                next_deadline: Some(25),
+                pending_events: 0,
+                run_count: 1,
+                last_run: Some(15),
+                execution_time: 300,
+                max_execution_time: 300,
+                deadline_misses: 0,
+                suspended: false,
+                active: true,
+            })
+        );
+        assert_eq!(
+            tasks.next(),
+            Some(TaskInfo {
+                name: "Idle task",
+                period: None,
+                offset: None,
+                next_deadline: None,
+                pending_events: 0x00000004,
+                run_count: 0,
+                last_run: None,
+                execution_time: 0,
+                max_execution_time: 0,
+                deadline_misses: 0,
+                suspended: true,
+                active: true,
+            })
+        );
+        assert_eq!(tasks.next(), None);
+        assert_eq!(scheduler.busy_time(), 300);
+
+        // Deadline at tick 25 and 35 elapsed without execution
+        assert!(scheduler.process_tasks(45));
+        let cyclic_task = scheduler.tasks().next().unwrap();
+        assert_eq!(cyclic_task.run_count, 2);
+        assert_eq!(cyclic_task.deadline_misses, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "stack-monitor")]
+    fn stack_high_water_mark_attribution() {
+        const TASK_COUNT: usize = 2;
+        fn shallow_init_runnable() {
+            port::stack::set_peak(256);
+        }
+        fn deep_process_runnable(_event_mask: EventMask) {
+            port::stack::set_peak(1024);
+        }
+        fn shallow_process_runnable(_event_mask: EventMask) {
+            port::stack::set_peak(512);
+        }
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new(
+            "Deep task",
+            Some(shallow_init_runnable),
+            Some(deep_process_runnable),
+            Some(10),
+            None,
+        ));
+        scheduler.add_task(Task::new(
+            "Shallow task",
+            None,
+            Some(shallow_process_runnable),
+            Some(10),
+            None,
+        ));
+        assert_eq!(scheduler.stack_high_water_mark(), None);
+
+        scheduler.init_tasks(0);
+        assert_eq!(
+            scheduler.stack_high_water_mark(),
+            Some(StackHighWaterMark {
+                used: 256,
+                size: 0,
+                task: Some("Deep task")
+            })
+        );
+
+        assert!(scheduler.process_tasks(10));
+        assert_eq!(
+            scheduler.stack_high_water_mark(),
+            Some(StackHighWaterMark {
+                used: 1024,
+                size: 0,
+                task: Some("Deep task")
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "budget")]
+    fn task_budget_overrun() {
+        const TASK_COUNT: usize = 1;
+        const TASK_NAME: &str = "Runaway task";
+        static OVERRUN_COUNT: AtomicU32 = AtomicU32::new(0);
+        fn overrun_hook(name: TaskName) -> OverrunAction {
+            assert_eq!(name, TASK_NAME);
+            OVERRUN_COUNT.fetch_add(1, Ordering::Relaxed);
+            OverrunAction::Continue
+        }
+        fn runaway_process_runnable(_event_mask: EventMask) {
+            // Tick interrupts occurring while the runnable executes
+            for _ in 0..5 {
+                port::advance_us(1_000);
+                budget::check();
+            }
+        }
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.register_overrun_hook(overrun_hook);
+        scheduler.add_task(
+            Task::new(
+                TASK_NAME,
+                None,
+                Some(runaway_process_runnable),
+                Some(10),
+                None,
+            )
+            .with_budget(2),
+        );
+        scheduler.init_tasks(0);
+
+        assert!(scheduler.process_tasks(10));
+        assert_eq!(OVERRUN_COUNT.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn mode_switching() {
+        const TASK_COUNT: usize = 2;
+        static CONTROL_RUNS: AtomicU32 = AtomicU32::new(0);
+        static DIAGNOSIS_RUNS: AtomicU32 = AtomicU32::new(0);
+        static HOOKS: AtomicU32 = AtomicU32::new(0);
+        fn control_process_runnable(_event_mask: EventMask) {
+            CONTROL_RUNS.fetch_add(1, Ordering::Relaxed);
+        }
+        fn diagnosis_process_runnable(_event_mask: EventMask) {
+            DIAGNOSIS_RUNS.fetch_add(1, Ordering::Relaxed);
+        }
+        fn normal_exit_hook() {
+            HOOKS.fetch_add(1, Ordering::Relaxed);
+        }
+        fn service_enter_hook() {
+            HOOKS.fetch_add(10, Ordering::Relaxed);
+        }
+        static MODES: [Mode; 2] = [
+            Mode::new(
+                "Normal",
+                &[modes::ModeTask::new("Control task", None)],
+                None,
+                Some(normal_exit_hook),
+            ),
+            Mode::new(
+                "Service",
+                &[
+                    modes::ModeTask::new("Control task", Some(20)),
+                    modes::ModeTask::new("Diagnosis task", None),
+                ],
+                Some(service_enter_hook),
+                None,
+            ),
+        ];
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new(
+            "Control task",
+            None,
+            Some(control_process_runnable),
+            Some(10),
+            None,
+        ));
+        scheduler.add_task(Task::new(
+            "Diagnosis task",
+            None,
+            Some(diagnosis_process_runnable),
+            Some(5),
+            Some(1),
+        ));
+        scheduler.register_modes(&MODES).unwrap();
+        assert_eq!(scheduler.current_mode(), None);
+        scheduler.init_tasks(0);
+
+        // Initial mode entered on the first pass, cycles restart from it
+        assert!(!scheduler.process_tasks(5));
+        assert_eq!(scheduler.current_mode(), Some("Normal"));
+        assert!(scheduler.process_tasks(15));
+        assert_eq!(CONTROL_RUNS.load(Ordering::Relaxed), 1);
+        assert_eq!(DIAGNOSIS_RUNS.load(Ordering::Relaxed), 0);
+
+        // Events of inactive tasks are kept until the task is activated
+        scheduler.set_task_event("Diagnosis task", 0x1);
+        assert!(!scheduler.process_tasks(16));
+
+        assert_eq!(
+            scheduler.switch_mode("Standby"),
+            Err(SchedulerError::UnknownMode)
+        );
+        scheduler.switch_mode("Service").unwrap();
+        assert_eq!(scheduler.current_mode(), Some("Normal"));
+        assert!(scheduler.process_tasks(20));
+        assert_eq!(scheduler.current_mode(), Some("Service"));
+        assert_eq!(HOOKS.load(Ordering::Relaxed), 11);
+        assert_eq!(DIAGNOSIS_RUNS.load(Ordering::Relaxed), 1);
+
+        let mut tasks = scheduler.tasks();
+        let control = tasks.next().unwrap();
+        assert_eq!(control.period, Some(20));
+        assert_eq!(control.next_deadline, Some(40));
+        assert_eq!(tasks.next().unwrap().next_deadline, Some(26));
+
+        // Back to the initial mode, the task cycle is restored
+        scheduler.switch_mode("Normal").unwrap();
+        assert!(!scheduler.process_tasks(21));
+        let control = scheduler.tasks().next().unwrap();
+        assert_eq!(control.period, Some(10));
+        assert!(!scheduler.tasks().nth(1).unwrap().active);
+
+        // Cycle changed at runtime is kept in place of the cycle of the modes
+        scheduler.set_task_period("Control task", 30).unwrap();
+        scheduler.switch_mode("Service").unwrap();
+        assert!(!scheduler.process_tasks(22));
+        let control = scheduler.tasks().next().unwrap();
+        assert_eq!(control.period, Some(30));
+        assert_eq!(control.next_deadline, Some(52));
+    }
+
+    #[test]
+    fn register_modes_errors() {
+        const TASK_COUNT: usize = 1;
+        static DUPLICATE: [Mode; 2] = [
+            Mode::new("Normal", &[], None, None),
+            Mode::new("Normal", &[], None, None),
+        ];
+        static UNKNOWN_TASK: [Mode; 1] = [Mode::new(
+            "Normal",
+            &[modes::ModeTask::new("Busy task", None)],
+            None,
+            None,
+        )];
+        static INVALID_CYCLE: [Mode; 1] = [Mode::new(
+            "Normal",
+            &[modes::ModeTask::new("Idle task", Some(0))],
+            None,
+            None,
+        )];
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new("Idle task", None, None, None, None));
+        assert_eq!(
+            scheduler.register_modes(&DUPLICATE),
+            Err(SchedulerError::Duplicate { existing: "Normal" })
+        );
+        assert_eq!(
+            scheduler.register_modes(&UNKNOWN_TASK),
+            Err(SchedulerError::UnknownTask)
+        );
+        assert_eq!(
+            scheduler.register_modes(&INVALID_CYCLE),
+            Err(SchedulerError::InvalidCycle)
+        );
+    }
+
+    #[test]
+    fn schedule_table_dispatching() {
+        const TASK_COUNT: usize = 3;
+        static SENSOR_RUNS: AtomicU32 = AtomicU32::new(0);
+        static ACTUATOR_RUNS: AtomicU32 = AtomicU32::new(0);
+        fn sensor_process_runnable(_event_mask: EventMask) {
+            SENSOR_RUNS.fetch_add(1, Ordering::Relaxed);
+        }
+        fn actuator_process_runnable(_event_mask: EventMask) {
+            ACTUATOR_RUNS.fetch_add(1, Ordering::Relaxed);
+        }
+        fn cyclic_process_runnable(_event_mask: EventMask) {}
+        static SCHEDULE_TABLE: schedule::ScheduleTable = schedule::ScheduleTable::new(
+            10,
+            &[
+                schedule::ExpiryPoint::new(0, &["Sensor task"]),
+                schedule::ExpiryPoint::new(4, &["Sensor task", "Actuator task"]),
+            ],
+        );
+        static CYCLIC_TABLE: schedule::ScheduleTable =
+            schedule::ScheduleTable::new(10, &[schedule::ExpiryPoint::new(0, &["Cyclic task"])]);
+        static UNKNOWN_TABLE: schedule::ScheduleTable =
+            schedule::ScheduleTable::new(10, &[schedule::ExpiryPoint::new(0, &["Busy task"])]);
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new(
+            "Sensor task",
+            None,
+            Some(sensor_process_runnable),
+            None,
+            None,
+        ));
+        scheduler.add_task(Task::new(
+            "Actuator task",
+            None,
+            Some(actuator_process_runnable),
+            None,
+            None,
+        ));
+        scheduler.add_task(Task::new(
+            "Cyclic task",
+            None,
+            Some(cyclic_process_runnable),
+            Some(100),
+            None,
+        ));
+        assert_eq!(
+            scheduler.register_schedule_table(&CYCLIC_TABLE),
+            Err(SchedulerError::InvalidScheduleTable)
+        );
+        assert_eq!(
+            scheduler.register_schedule_table(&UNKNOWN_TABLE),
+            Err(SchedulerError::UnknownTask)
+        );
+        scheduler.register_schedule_table(&SCHEDULE_TABLE).unwrap();
+        scheduler.init_tasks(0);
+
+        assert!(scheduler.process_tasks(0));
+        assert!(!scheduler.process_tasks(3));
+        assert!(scheduler.process_tasks(4));
+        assert!(!scheduler.process_tasks(9));
+        assert_eq!(SENSOR_RUNS.load(Ordering::Relaxed), 2);
+        assert_eq!(ACTUATOR_RUNS.load(Ordering::Relaxed), 1);
+
+        // Table repeated, the expiry point of tick 14 is missed by the sensor task
+        assert!(scheduler.process_tasks(10));
+        assert!(scheduler.process_tasks(20));
+        assert_eq!(SENSOR_RUNS.load(Ordering::Relaxed), 4);
+        assert_eq!(ACTUATOR_RUNS.load(Ordering::Relaxed), 2);
+        let sensor = scheduler.tasks().next().unwrap();
+        assert_eq!(sensor.deadline_misses, 1);
+        assert_eq!(sensor.next_deadline, None);
+    }
+
+    #[test]
+    fn schedule_table_mode_cycle() {
+        const TASK_COUNT: usize = 1;
+        fn dummy_process_runnable(_event_mask: EventMask) {}
+        static SCHEDULE_TABLE: schedule::ScheduleTable =
+            schedule::ScheduleTable::new(10, &[schedule::ExpiryPoint::new(0, &["Sensor task"])]);
+        static CYCLIC_MODES: [Mode; 1] = [Mode::new(
+            "Normal",
+            &[modes::ModeTask::new("Sensor task", Some(5))],
+            None,
+            None,
+        )];
+        static MODES: [Mode; 1] = [Mode::new(
+            "Normal",
+            &[modes::ModeTask::new("Sensor task", None)],
+            None,
+            None,
+        )];
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new(
+            "Sensor task",
+            None,
+            Some(dummy_process_runnable),
+            None,
+            None,
+        ));
+        // Rejected whichever is registered first
+        scheduler.register_modes(&CYCLIC_MODES).unwrap();
+        assert_eq!(
+            scheduler.register_schedule_table(&SCHEDULE_TABLE),
+            Err(SchedulerError::InvalidScheduleTable)
+        );
+        scheduler.register_modes(&MODES).unwrap();
+        scheduler.register_schedule_table(&SCHEDULE_TABLE).unwrap();
+        assert_eq!(
+            scheduler.register_modes(&CYCLIC_MODES),
+            Err(SchedulerError::InvalidScheduleTable)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn schedule_table_unordered_expiry_points() {
+        static EXPIRY_POINTS: [schedule::ExpiryPoint; 2] = [
+            schedule::ExpiryPoint::new(5, &["Dummy task"]),
+            schedule::ExpiryPoint::new(5, &["Dummy task"]),
+        ];
+        schedule::ScheduleTable::new(10, &EXPIRY_POINTS);
+    }
+
+    #[test]
+    fn offset_assignment() {
+        const TASK_COUNT: usize = 4;
+        // Runnables of distinct bodies, identical ones may be merged by optimized builds
+        fn dummy_process_runnable1(_event_mask: EventMask) {
+            core::hint::black_box(1);
+        }
+        fn dummy_process_runnable2(_event_mask: EventMask) {
+            core::hint::black_box(2);
+        }
+        fn dummy_process_runnable3(_event_mask: EventMask) {
+            core::hint::black_box(3);
+        }
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new(
+            "Slow task",
+            None,
+            Some(dummy_process_runnable1),
+            Some(20),
+            None,
+        ));
+        scheduler.add_task(Task::new(
+            "Fixed task",
+            None,
+            Some(dummy_process_runnable2),
+            Some(10),
+            Some(0),
+        ));
+        scheduler.add_task(Task::new(
+            "Fast task",
+            None,
+            Some(dummy_process_runnable3),
+            Some(10),
+            None,
+        ));
+        scheduler.add_task(Task::new("Event task", None, None, None, None));
+        scheduler.enable_offset_assignment();
+        scheduler.init_tasks(0);
+
+        let offsets: std::vec::Vec<_> = scheduler.tasks().map(|task| task.offset).collect();
+        assert_eq!(offsets, [Some(2), Some(0), Some(1), None]);
+        assert_eq!(scheduler.tasks().next().unwrap().next_deadline, Some(22));
+    }
+
+    #[test]
+    fn dispatch_policies() {
+        const TASK_COUNT: usize = 3;
+        static FIXED_PRIORITY: dispatch::FixedPriority = dispatch::FixedPriority;
+        static EARLIEST_DEADLINE_FIRST: dispatch::EarliestDeadlineFirst =
+            dispatch::EarliestDeadlineFirst;
+        // Dispatched task identifiers, one digit per dispatch
+        static ORDER: AtomicU32 = AtomicU32::new(0);
+        fn record(id: u32) {
+            ORDER
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |order| {
+                    Some(order * 10 + id)
+                })
+                .unwrap();
+        }
+        fn process_runnable1(_event_mask: EventMask) {
+            record(1);
+        }
+        fn process_runnable2(_event_mask: EventMask) {
+            record(2);
+        }
+        fn process_runnable3(_event_mask: EventMask) {
+            record(3);
+        }
+        fn run_pass<const N: usize>(scheduler: &Scheduler<N, DUMMY_CORE_FREQ>) -> u32 {
+            ORDER.store(0, Ordering::Relaxed);
+            scheduler.init_tasks(0);
+            scheduler.set_task_event("Event task", 0x1);
+            while scheduler.process_tasks(10) {}
+            ORDER.load(Ordering::Relaxed)
+        }
+
+        let tasks = || {
+            [
+                Task::new("Slow task", None, Some(process_runnable1), Some(10), None),
+                Task::new("Fast task", None, Some(process_runnable2), Some(5), Some(3))
+                    .with_priority(5),
+                Task::new("Event task", None, Some(process_runnable3), None, None).with_priority(1),
+            ]
+        };
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        tasks()
+            .into_iter()
+            .for_each(|task| scheduler.add_task(task));
+        assert_eq!(run_pass(&scheduler), 123);
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> =
+            Scheduler::with_dispatch_policy(&FIXED_PRIORITY);
+        tasks()
+            .into_iter()
+            .for_each(|task| scheduler.add_task(task));
+        assert_eq!(run_pass(&scheduler), 231);
+
+        // Fast task is due at tick 8 and slow task at tick 10
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> =
+            Scheduler::with_dispatch_policy(&EARLIEST_DEADLINE_FIRST);
+        tasks()
+            .into_iter()
+            .for_each(|task| scheduler.add_task(task));
+        assert_eq!(run_pass(&scheduler), 213);
+    }
+
+    #[test]
+    fn typed_task_events() {
+        const TASK_COUNT: usize = 1;
+        const TASK_NAME: &str = "Dummy task";
+
+        #[non_preemptive_scheduler_macros::events]
+        enum DummyEvents {
+            First,
+            Second,
+            Third,
+        }
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new(TASK_NAME, None, None, None, None));
+
+        scheduler.set_task_event_typed(TASK_NAME, DummyEvents::First | DummyEvents::Third);
+        assert_eq!(scheduler.get_task_event(TASK_NAME), Some(0x00000005));
+        scheduler.clear_task_event_typed(TASK_NAME, DummyEvents::First);
+        scheduler.set_task_event_typed(TASK_NAME, DummyEvents::Second);
+        assert_eq!(scheduler.get_task_event(TASK_NAME), Some(0x00000006));
+    }
+
+    #[test]
+    fn static_scheduler_access() {
+        const TASK_COUNT: usize = 1;
+        const TASK_NAME: &str = "Dummy task";
+        const TASK_EVENT: EventMask = 0x00000001;
+        static SCHEDULER: StaticScheduler<TASK_COUNT, DUMMY_CORE_FREQ> = StaticScheduler::new();
+
+        SCHEDULER.configure(|scheduler| {
+            scheduler.add_task(Task::new(TASK_NAME, None, None, None, None));
+            // Shared access is not granted while configuring
+            assert_eq!(SCHEDULER.with(|_| ()), None);
+            assert_eq!(SCHEDULER.in_thread_mode(|_| ()), None);
+        });
+        assert_eq!(
+            SCHEDULER.in_thread_mode(|scheduler| scheduler.set_task_period(TASK_NAME, 10)),
+            Some(Err(SchedulerError::NotCyclic))
+        );
+
+        SCHEDULER.with(|scheduler| scheduler.set_task_event(TASK_NAME, TASK_EVENT));
+        assert_eq!(
+            SCHEDULER.with(|scheduler| scheduler.get_task_event(TASK_NAME)),
+            Some(Some(TASK_EVENT))
+        );
+    }
+
+    #[test]
+    fn static_scheduler_event_while_configuring() {
+        const TASK_COUNT: usize = 1;
+        const TASK_NAME: &str = "Dummy task";
+        static SCHEDULER: StaticScheduler<TASK_COUNT, DUMMY_CORE_FREQ> = StaticScheduler::new();
+
+        SCHEDULER.configure(|scheduler| {
+            scheduler.add_task(Task::new(TASK_NAME, None, None, None, None));
+            // Event raised by an interrupt during configuration is dropped
+            assert_eq!(
+                SCHEDULER.with(|scheduler| scheduler.set_task_event(TASK_NAME, 0x00000001)),
+                None
+            );
+        });
+        assert_eq!(
+            SCHEDULER.with(|scheduler| scheduler.get_task_event(TASK_NAME)),
+            Some(Some(0))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn static_scheduler_configuration_while_accessed() {
+        const TASK_COUNT: usize = 1;
+        static SCHEDULER: StaticScheduler<TASK_COUNT, DUMMY_CORE_FREQ> = StaticScheduler::new();
+
+        SCHEDULER.with(|_| SCHEDULER.configure(|_| ()));
+    }
 }
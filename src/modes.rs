@@ -0,0 +1,53 @@
+//! Operating modes, each of them activating a subset of the tasks with optional
+//! mode specific execution cycles
+
+use crate::TaskName;
+
+pub type ModeName = &'static str;
+pub type ModeHook = fn();
+
+/// Task active in a mode, the execution cycle overrides the one of the task if any
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModeTask {
+    pub(crate) name: TaskName,
+    pub(crate) execution_cycle: Option<u32>,
+}
+
+impl ModeTask {
+    pub const fn new(name: TaskName, execution_cycle: Option<u32>) -> ModeTask {
+        ModeTask {
+            name,
+            execution_cycle,
+        }
+    }
+}
+
+/// Mode with its active tasks, the exit hook of the current mode and the enter
+/// hook of the new one are executed on switching
+#[derive(Debug)]
+pub struct Mode {
+    pub(crate) name: ModeName,
+    pub(crate) tasks: &'static [ModeTask],
+    pub(crate) enter_hook: Option<ModeHook>,
+    pub(crate) exit_hook: Option<ModeHook>,
+}
+
+impl Mode {
+    pub const fn new(
+        name: ModeName,
+        tasks: &'static [ModeTask],
+        enter_hook: Option<ModeHook>,
+        exit_hook: Option<ModeHook>,
+    ) -> Mode {
+        Mode {
+            name,
+            tasks,
+            enter_hook,
+            exit_hook,
+        }
+    }
+
+    pub(crate) fn task(&self, name: TaskName) -> Option<&ModeTask> {
+        self.tasks.iter().find(|task| task.name == name)
+    }
+}
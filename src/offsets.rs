@@ -0,0 +1,57 @@
+//! Assignment of the execution offsets of the cyclic tasks which do not specify one,
+//! spreading the tasks so that as few of them as possible are due on the same tick
+
+/// Fixed point scale of the collision rates
+const RATE_SCALE: u64 = 1 << 32;
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Offset within the period which minimises the rate of ticks shared with the tasks
+/// already placed, given as `(period, offset)`. Two tasks are due on the same tick
+/// whenever their offsets are congruent modulo the greatest common divisor of their
+/// periods, which happens once per least common multiple of the periods.
+/// Ties are resolved with the lowest offset
+pub(crate) fn best_offset(period: u32, placed: impl Iterator<Item = (u32, u32)> + Clone) -> u32 {
+    let mut best = (u64::MAX, 0);
+    for offset in 0..period {
+        let collisions: u64 = placed
+            .clone()
+            .filter_map(|(other_period, other_offset)| {
+                let gcd = gcd(period, other_period);
+                (offset % gcd == other_offset % gcd).then(|| {
+                    let lcm = (period / gcd) as u64 * other_period as u64;
+                    RATE_SCALE / lcm
+                })
+            })
+            .sum();
+        if collisions < best.0 {
+            best = (collisions, offset);
+            if collisions == 0 {
+                break;
+            }
+        }
+    }
+    best.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_spread() {
+        // Nothing placed yet
+        assert_eq!(best_offset(10, [].into_iter()), 0);
+        // Free slot next to a task of the same period
+        assert_eq!(best_offset(10, [(10, 0)].into_iter()), 1);
+        // Periods 4 and 6 share ticks whenever offsets are congruent modulo 2
+        assert_eq!(best_offset(6, [(4, 0), (4, 2)].into_iter()), 1);
+        // Every offset collides, the task of the longest period is the least frequent
+        assert_eq!(best_offset(2, [(2, 0), (4, 1)].into_iter()), 1);
+    }
+}
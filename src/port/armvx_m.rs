@@ -1,6 +1,7 @@
 //! Abstractions for Arm Cortex-M
 
 use cortex_m::interrupt::free as critical_section;
+use cortex_m::peripheral::{scb::VectActive, SCB};
 use cortex_m_rt::exception;
 use volatile_register::RW;
 
@@ -12,6 +13,7 @@ pub struct SysTick {
 impl SysTick {
     const SYST_CSR: *mut RW<u32> = 0xE000E010 as *mut _;
     const SYST_RVR: *mut RW<u32> = 0xE000E014 as *mut _;
+    const SYST_CVR: *mut RW<u32> = 0xE000E018 as *mut _;
     const SYST_CSR_COUNTER_ENABLE: u32 = 1 << 0;
     const SYST_CSR_TICK_INT_ENABLE: u32 = 1 << 1;
     const SYST_CSR_TICK_PROCESSOR_AS_CLCK_SOURCE: u32 = 1 << 2;
@@ -47,11 +49,115 @@ impl SysTick {
     }
 }
 
+/// Time in microseconds derived from the tick and the SysTick counter, wrapping on overflow
+pub fn now_us() -> u32 {
+    critical_section(|_| unsafe {
+        let reload = (*SysTick::SYST_RVR).read();
+        let mut value = (*SysTick::SYST_CVR).read();
+        let mut tick = TICK;
+        // Counter wrapped but the tick is not incremented yet
+        if SCB::is_pendst_pending() {
+            tick = tick.wrapping_add(1);
+            value = (*SysTick::SYST_CVR).read();
+        }
+        let elapsed = (reload - value) as u64 * 1_000 / (reload as u64 + 1);
+        tick.wrapping_mul(1_000).wrapping_add(elapsed as u32)
+    })
+}
+
+/// Whether the core executes in thread mode, i.e. no exception or interrupt is active
+pub fn is_thread_mode() -> bool {
+    SCB::vect_active() == VectActive::ThreadMode
+}
+
 #[exception]
 fn SysTick() {
     unsafe {
         TICK += 1;
     }
+    #[cfg(feature = "budget")]
+    crate::budget::check();
+}
+
+#[cfg(feature = "budget")]
+pub fn reset() -> ! {
+    SCB::sys_reset()
+}
+
+/// Stack painting, the region from the stack pointer at launch down to `_stack_limit`,
+/// which the linker script of the application defines as the lowest address of the stack,
+/// is filled with a known pattern which executions overwrite. Nothing below it is painted,
+/// e.g. a heap placed under the stack
+#[cfg(feature = "stack-monitor")]
+pub mod stack {
+    use core::{
+        ptr::addr_of,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    const PAINT: u32 = 0xACCE_55ED;
+    /// Words left unpainted below the stack pointer, used by the painting itself
+    const MARGIN: usize = 64;
+    /// Consecutive painted words ending the search of the deepest overwritten word, the
+    /// peak usage can be missed if an execution skips that many words, e.g. an unwritten
+    /// buffer, while going deeper
+    const GAP: usize = 16;
+
+    extern "C" {
+        static _stack_limit: u32;
+        static _stack_start: u32;
+    }
+
+    /// Deepest overwritten word found so far, zero until the stack is painted
+    static WATERMARK: AtomicUsize = AtomicUsize::new(0);
+
+    // Access to extern statics is no longer unsafe since rust 1.82.0
+    #[allow(unused_unsafe)]
+    fn bottom() -> usize {
+        unsafe { addr_of!(_stack_limit) as usize }
+    }
+
+    #[allow(unused_unsafe)]
+    fn top() -> usize {
+        unsafe { addr_of!(_stack_start) as usize }
+    }
+
+    /// Paints the unused stack, i.e. from below the current stack pointer down to the limit
+    pub fn paint() {
+        let end = cortex_m::register::msp::read() as usize - MARGIN * 4;
+        let mut word = end as *mut u32;
+        while word as usize > bottom() {
+            unsafe {
+                word = word.sub(1);
+                word.write_volatile(PAINT);
+            }
+        }
+        WATERMARK.store(end, Ordering::Relaxed);
+    }
+
+    /// Peak stack usage in bytes since painting. The search resumes downwards from the
+    /// last watermark, so that it only covers the stack used since the previous call
+    pub fn peak() -> Option<u32> {
+        let watermark = WATERMARK.load(Ordering::Relaxed);
+        if watermark == 0 {
+            return None;
+        }
+        let (mut word, mut deepest, mut painted) = (watermark as *const u32, watermark, 0);
+        while word as usize > bottom() && painted < GAP {
+            word = unsafe { word.sub(1) };
+            if unsafe { word.read_volatile() } == PAINT {
+                painted += 1;
+            } else {
+                (deepest, painted) = (word as usize, 0);
+            }
+        }
+        WATERMARK.store(deepest, Ordering::Relaxed);
+        Some((top() - deepest) as u32)
+    }
+
+    pub fn size() -> u32 {
+        (top() - bottom()) as u32
+    }
 }
 
 #[cfg(feature = "panic")]
@@ -59,14 +165,14 @@ pub mod panic {
     use core::panic::PanicInfo;
     #[cfg(debug_assertions)]
     use cortex_m::asm::bkpt;
-    #[cfg(debug_assertions)]
-    use rtt_target::rprintln as log;
     #[inline(never)]
     #[panic_handler]
     #[allow(unused_variables)]
     fn panic(info: &PanicInfo) -> ! {
-        #[cfg(debug_assertions)]
-        log!("{}", info);
+        #[cfg(all(debug_assertions, feature = "defmt"))]
+        defmt::error!("{}", defmt::Display2Format(info));
+        #[cfg(all(debug_assertions, feature = "rtt-target", not(feature = "defmt")))]
+        rtt_target::rprintln!("{}", info);
         loop {
             #[cfg(debug_assertions)]
             bkpt();
@@ -1,23 +1,76 @@
+/// Logger used when no logging backend is selected, arguments are type checked only
+#[allow(unused_macros)]
+macro_rules! discard {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    };
+}
+
 #[cfg(feature = "armvx-m")]
 mod armvx_m;
+#[cfg(all(feature = "armvx-m", feature = "budget"))]
+pub use armvx_m::reset;
+#[cfg(all(feature = "armvx-m", feature = "stack-monitor"))]
+pub use armvx_m::stack;
 #[cfg(feature = "armvx-m")]
-pub use cortex_m::interrupt::free as critical_section;
+pub use armvx_m::{is_thread_mode, now_us};
 #[cfg(feature = "armvx-m")]
-pub type Mutex<T> = cortex_m::interrupt::Mutex<T>;
+pub use cortex_m::interrupt::free as critical_section;
 #[cfg(feature = "armvx-m")]
 pub type SysTick = armvx_m::SysTick;
-#[cfg(feature = "armvx-m")]
-pub use rtt_target::rprintln as log;
+#[cfg(all(feature = "armvx-m", feature = "defmt"))]
+#[allow(unused_imports)]
+pub(crate) use defmt::{error as log_error_backend, info as log, trace as log_trace_backend};
+#[cfg(all(feature = "armvx-m", feature = "rtt-target", not(feature = "defmt")))]
+#[allow(unused_imports)]
+pub(crate) use rtt_target::{
+    rprintln as log, rprintln as log_error_backend, rprintln as log_trace_backend,
+};
+#[cfg(all(
+    feature = "armvx-m",
+    not(any(feature = "rtt-target", feature = "defmt"))
+))]
+#[allow(unused_imports)]
+pub(crate) use {discard as log, discard as log_error_backend, discard as log_trace_backend};
 #[cfg(feature = "x86")]
 mod x86;
+#[cfg(all(feature = "x86", test))]
+pub use x86::advance_us;
 #[cfg(feature = "x86")]
 pub use x86::critical_section;
+#[cfg(all(feature = "x86", feature = "budget", test))]
+pub use x86::reset;
+#[cfg(all(feature = "x86", feature = "stack-monitor"))]
+pub use x86::stack;
 #[cfg(feature = "x86")]
-pub type Mutex<T> = x86::Mutex<T>;
+pub use x86::{is_thread_mode, now_us};
 #[cfg(feature = "x86")]
 pub type SysTick = x86::SysTick;
-#[cfg(feature = "x86")]
-pub(crate) use println as log;
+#[cfg(all(feature = "x86", feature = "log"))]
+#[allow(unused_imports)]
+pub(crate) use ::log::{error as log_error_backend, info as log, trace as log_trace_backend};
+#[cfg(all(feature = "x86", not(feature = "log")))]
+#[allow(unused_imports)]
+pub(crate) use {println as log, println as log_error_backend, println as log_trace_backend};
+
+// Scheduler messages are governed by the log level features, `log-off` takes
+// precedence and each level enables the ones below it
+#[cfg(not(all(feature = "log-error", not(feature = "log-off"))))]
+pub(crate) use discard as log_error;
+#[cfg(not(all(feature = "log-info", not(feature = "log-off"))))]
+pub(crate) use discard as log_info;
+#[cfg(not(all(feature = "log-trace", not(feature = "log-off"))))]
+pub(crate) use discard as log_trace;
+#[cfg(all(feature = "log-info", not(feature = "log-off")))]
+pub(crate) use log as log_info;
+#[cfg(all(feature = "log-error", not(feature = "log-off")))]
+#[allow(unused_imports)]
+pub(crate) use log_error_backend as log_error;
+#[cfg(all(feature = "log-trace", not(feature = "log-off")))]
+#[allow(unused_imports)]
+pub(crate) use log_trace_backend as log_trace;
 
 #[cfg(feature = "panic")]
 mod panic {
@@ -1,6 +1,7 @@
 //! Abstractions for x86
 
-use std::marker::PhantomData;
+#[cfg(not(test))]
+use core::sync::atomic::{AtomicU32, Ordering};
 
 pub struct SysTick {
     _core_freq: u32,
@@ -30,12 +31,106 @@ impl SysTick {
     }
 }
 
-pub struct Mutex<T> {
-    _marker: PhantomData<T>,
+/// Simulated time in microseconds, only advanced explicitly
+#[cfg(not(test))]
+static NOW_US: AtomicU32 = AtomicU32::new(0);
+
+#[cfg(not(test))]
+pub fn now_us() -> u32 {
+    NOW_US.load(Ordering::Relaxed)
+}
+
+// Tests run in parallel, each of them gets its own time
+#[cfg(test)]
+std::thread_local! {
+    static NOW_US: core::cell::Cell<u32> = const { core::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+pub fn now_us() -> u32 {
+    NOW_US.with(|now_us| now_us.get())
+}
+
+#[cfg(test)]
+pub fn advance_us(us: u32) {
+    NOW_US.with(|now_us| now_us.set(now_us.get() + us));
+}
+
+/// There is no stack painting on the host, tests simulate the stack usage
+#[cfg(feature = "stack-monitor")]
+pub mod stack {
+    #[cfg(test)]
+    std::thread_local! {
+        static PEAK: core::cell::Cell<Option<u32>> = const { core::cell::Cell::new(None) };
+    }
+
+    pub fn paint() {}
+
+    #[cfg(not(test))]
+    pub fn peak() -> Option<u32> {
+        None
+    }
+
+    #[cfg(test)]
+    pub fn peak() -> Option<u32> {
+        PEAK.with(|peak| peak.get())
+    }
+
+    #[cfg(test)]
+    pub fn set_peak(peak: u32) {
+        PEAK.with(|current| current.set(Some(peak)));
+    }
+
+    pub fn size() -> u32 {
+        0
+    }
+}
+
+#[cfg(all(test, feature = "budget"))]
+pub fn reset() -> ! {
+    panic!("System reset requested");
+}
+
+/// There are no interrupts on the host, execution always happens in thread mode
+pub fn is_thread_mode() -> bool {
+    true
 }
 
 pub struct CriticalSection;
 
+/// Threads of the host run in parallel, so the critical section is a global lock.
+/// It may be entered again by the thread holding it, as nested interrupt free sections
+/// are on the target
+#[cfg(any(test, feature = "std"))]
+pub fn critical_section<F, R>(f: F) -> R
+where
+    F: FnOnce(CriticalSection) -> R,
+{
+    use std::sync::{Mutex, PoisonError};
+
+    static LOCK: Mutex<()> = Mutex::new(());
+    std::thread_local! {
+        static NESTED: core::cell::Cell<bool> = const { core::cell::Cell::new(false) };
+    }
+
+    if NESTED.with(|nested| nested.get()) {
+        return f(CriticalSection {});
+    }
+    // A panic within a critical section does not leave any state behind
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    NESTED.with(|nested| nested.set(true));
+    struct Exit;
+    impl Drop for Exit {
+        fn drop(&mut self) {
+            NESTED.with(|nested| nested.set(false));
+        }
+    }
+    let _exit = Exit;
+    f(CriticalSection {})
+}
+
+/// Without `std` there are no threads, the critical section only runs the closure
+#[cfg(not(any(test, feature = "std")))]
 pub fn critical_section<F, R>(f: F) -> R
 where
     F: FnOnce(CriticalSection) -> R,
@@ -1,28 +1,277 @@
 //! Abstractions to handle static resources
 
-use crate::port::Mutex;
-use core::cell::UnsafeCell;
+use crate::port::{critical_section, is_thread_mode};
+use core::{
+    cell::{Cell, Ref, RefCell, RefMut, UnsafeCell},
+    fmt,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+};
 
 /// UnsafeCell wrapper for resources which are shared between
-/// different execution contexts.
-pub type Shared<T> = Mutex<T>;
+/// different execution contexts, e.g. tasks and interrupt handlers.
+/// Resource is only accessed within a critical section.
+pub struct Shared<T> {
+    inner: UnsafeCell<T>,
+    locked: Cell<bool>,
+}
+
+impl<T> Shared<T> {
+    pub const fn new(value: T) -> Self {
+        Shared {
+            inner: UnsafeCell::new(value),
+            locked: Cell::new(false),
+        }
+    }
+
+    /// Enters a critical section and hands out a mutable reference to the resource,
+    /// panics if the resource is already locked, i.e. upon nested locking
+    pub fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        critical_section(|_| {
+            if self.locked.replace(true) {
+                panic!("Shared resource already locked");
+            }
+            // Safety: interrupts are disabled and the resource is not locked elsewhere
+            let result = f(unsafe { &mut *self.inner.get() });
+            self.locked.set(false);
+            result
+        })
+    }
+}
+
+unsafe impl<T> Sync for Shared<T> where T: Send {}
 
-/// UnsafeCell wrapper for resources which are not shared between
-/// different execution contexts.
+impl<T> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shared").finish_non_exhaustive()
+    }
+}
+
+/// Resources which are not shared must only be accessed from thread mode
+#[inline]
+fn check_context() {
+    debug_assert!(
+        is_thread_mode(),
+        "Resource not shared accessed out of thread mode"
+    );
+}
+
+/// RefCell wrapper for resources which are not shared between
+/// different execution contexts, i.e. only accessed from super loop tasks.
+/// Access from an interrupt or exception handler is detected in debug builds.
 pub struct UnShared<T> {
-    inner: UnsafeCell<T>,
+    inner: RefCell<T>,
 }
 
 impl<T> UnShared<T> {
     pub const fn new(value: T) -> Self {
         UnShared {
-            inner: UnsafeCell::new(value),
+            inner: RefCell::new(value),
         }
     }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        check_context();
+        self.inner.borrow()
+    }
+
     #[allow(clippy::should_implement_trait)]
-    pub fn borrow(&self) -> &T {
-        unsafe { &*self.inner.get() }
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        check_context();
+        self.inner.borrow_mut()
     }
 }
 
+impl<T> UnShared<Option<T>> {
+    /// Initializes a resource declared as `None`, panics if already initialized
+    pub fn init_once(&self, value: T) {
+        if self.borrow_mut().replace(value).is_some() {
+            panic!("UnShared resource already initialized");
+        }
+    }
+}
+
+// Resource is only accessed from thread mode, which is checked in debug builds
 unsafe impl<T> Sync for UnShared<T> where T: Send {}
+
+/// Resource which is not shared between different execution contexts and is
+/// initialized once at runtime, typically from an init runnable, before being
+/// accessed by process runnables without any `Option` handling.
+/// Access before initialization or while already borrowed panics, access from an
+/// interrupt or exception handler is detected in debug builds.
+pub struct Late<T> {
+    inner: UnsafeCell<MaybeUninit<T>>,
+    initialized: Cell<bool>,
+    borrowed: Cell<bool>,
+}
+
+impl<T> Late<T> {
+    pub const fn new() -> Self {
+        Late {
+            inner: UnsafeCell::new(MaybeUninit::uninit()),
+            initialized: Cell::new(false),
+            borrowed: Cell::new(false),
+        }
+    }
+
+    /// Initializes the resource, panics if already initialized
+    pub fn init(&self, value: T) {
+        check_context();
+        if self.initialized.get() {
+            panic!("Late resource already initialized");
+        }
+        // Safety: resource is not initialized yet, hence not borrowed
+        unsafe { (*self.inner.get()).write(value) };
+        self.initialized.set(true);
+    }
+
+    #[inline]
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.get()
+    }
+
+    /// Gives access to the resource, panics if it is not initialized or already borrowed
+    #[inline]
+    pub fn get(&self) -> LateRef<'_, T> {
+        check_context();
+        if !self.initialized.get() {
+            panic!("Late resource accessed before initialization");
+        }
+        if self.borrowed.replace(true) {
+            panic!("Late resource already borrowed");
+        }
+        LateRef { late: self }
+    }
+}
+
+impl<T> Default for Late<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Late<T> {
+    fn drop(&mut self) {
+        if self.initialized.get() {
+            // Safety: resource is initialized and exclusively owned
+            unsafe { self.inner.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+// Resource is only accessed from thread mode, which is checked in debug builds, and
+// the initialization and exclusive borrow are checked at runtime
+unsafe impl<T> Sync for Late<T> where T: Send {}
+
+/// Exclusive access to an initialized [`Late`] resource
+pub struct LateRef<'a, T> {
+    late: &'a Late<T>,
+}
+
+impl<T> Deref for LateRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: resource is initialized and exclusively borrowed by this guard
+        unsafe { (*self.late.inner.get()).assume_init_ref() }
+    }
+}
+
+impl<T> DerefMut for LateRef<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: resource is initialized and exclusively borrowed by this guard
+        unsafe { (*self.late.inner.get()).assume_init_mut() }
+    }
+}
+
+impl<T> Drop for LateRef<'_, T> {
+    fn drop(&mut self) {
+        self.late.borrowed.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_lock() {
+        static COUNTER: Shared<u32> = Shared::new(0);
+
+        COUNTER.lock(|counter| *counter += 1);
+        assert_eq!(COUNTER.lock(|counter| *counter), 1);
+    }
+
+    #[test]
+    fn shared_lock_across_threads() {
+        static COUNTER: Shared<u32> = Shared::new(0);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..1_000 {
+                        COUNTER.lock(|counter| *counter += 1);
+                    }
+                });
+            }
+        });
+        assert_eq!(COUNTER.lock(|counter| *counter), 4_000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn shared_nested_lock() {
+        let counter = Shared::new(0u32);
+
+        counter.lock(|_| counter.lock(|counter| *counter += 1));
+    }
+
+    #[test]
+    fn unshared_init_once() {
+        let led: UnShared<Option<bool>> = UnShared::new(None);
+
+        led.init_once(false);
+        if let Some(led) = led.borrow_mut().as_mut() {
+            *led = true;
+        }
+        assert_eq!(*led.borrow(), Some(true));
+    }
+
+    #[test]
+    #[should_panic]
+    fn unshared_init_twice() {
+        let led: UnShared<Option<bool>> = UnShared::new(None);
+
+        led.init_once(false);
+        led.init_once(true);
+    }
+
+    #[test]
+    fn late_init_and_get() {
+        let led: Late<bool> = Late::new();
+        assert!(!led.is_initialized());
+
+        led.init(false);
+        *led.get() = true;
+        assert!(*led.get());
+    }
+
+    #[test]
+    #[should_panic]
+    fn late_get_before_init() {
+        let led: Late<bool> = Late::new();
+
+        led.get();
+    }
+
+    #[test]
+    #[should_panic]
+    fn late_nested_get() {
+        let led: Late<bool> = Late::new();
+
+        led.init(false);
+        let _led = led.get();
+        led.get();
+    }
+}
@@ -0,0 +1,61 @@
+//! Time-triggered schedule tables, an alternative to the execution cycle of the tasks
+//! where the tasks are dispatched at fixed expiry points within a repeating period
+
+use crate::TaskName;
+
+/// Expiry point of a schedule table, the tasks are dispatched once the offset in
+/// milliseconds from the start of the table period is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiryPoint {
+    pub(crate) offset: u32,
+    pub(crate) tasks: &'static [TaskName],
+}
+
+impl ExpiryPoint {
+    pub const fn new(offset: u32, tasks: &'static [TaskName]) -> ExpiryPoint {
+        ExpiryPoint { offset, tasks }
+    }
+}
+
+/// Schedule table repeated every `duration` milliseconds, usually the hyperperiod of the
+/// tasks. Being a const constructor, the layout of a table declared as a static is
+/// checked at compile time, whereas its tasks are checked when registered
+#[derive(Debug)]
+pub struct ScheduleTable {
+    pub(crate) duration: u32,
+    pub(crate) expiry_points: &'static [ExpiryPoint],
+}
+
+impl ScheduleTable {
+    /// Panics if the duration is zero or the expiry point offsets are not strictly
+    /// increasing and lower than the duration
+    pub const fn new(duration: u32, expiry_points: &'static [ExpiryPoint]) -> ScheduleTable {
+        assert!(
+            duration > 0,
+            "Schedule table duration must be greater than 0"
+        );
+        let mut index = 0;
+        while index < expiry_points.len() {
+            let offset = expiry_points[index].offset;
+            assert!(
+                offset < duration,
+                "Expiry point offset must be lower than the schedule table duration"
+            );
+            assert!(
+                index == 0 || offset > expiry_points[index - 1].offset,
+                "Expiry point offsets must be strictly increasing"
+            );
+            index += 1;
+        }
+        ScheduleTable {
+            duration,
+            expiry_points,
+        }
+    }
+
+    pub(crate) fn tasks(&self) -> impl Iterator<Item = TaskName> + '_ {
+        self.expiry_points
+            .iter()
+            .flat_map(|expiry_point| expiry_point.tasks.iter().copied())
+    }
+}
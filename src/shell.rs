@@ -0,0 +1,320 @@
+//! Line based debug shell to inspect and control the scheduler over any byte stream,
+//! received bytes are fed to the shell and responses are written to an `embedded_io` writer.
+//!
+//! Commands:
+//! - `tasks`: configuration and state of each task
+//! - `stats`: execution statistics of each task
+//! - `event <task> <mask>`: sets events on a task, mask in decimal or `0x` hexadecimal
+//! - `suspend <task>` / `resume <task>`: suspends or resumes a task
+//! - `period <task> <ms>`: changes the execution cycle of a cyclic task
+//! - `mode [<mode>]`: shows the current mode or switches to another one
+
+use crate::{EventMask, Scheduler, TaskInfo};
+use core::fmt::{self, Write as _};
+use embedded_io::Write;
+use heapless::Vec;
+
+pub struct Shell<const LINE_SIZE: usize> {
+    line: Vec<u8, LINE_SIZE>,
+    overflow: bool,
+}
+
+impl<const LINE_SIZE: usize> Default for Shell<LINE_SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const LINE_SIZE: usize> Shell<LINE_SIZE> {
+    pub const fn new() -> Shell<LINE_SIZE> {
+        Shell {
+            line: Vec::new(),
+            overflow: false,
+        }
+    }
+
+    /// Feeds received bytes to the shell, each complete line is executed as a command
+    /// on the scheduler and its response written to `output`
+    pub fn process<W: Write, const TASK_COUNT: usize, const CORE_FREQ: u32>(
+        &mut self,
+        scheduler: &Scheduler<TASK_COUNT, CORE_FREQ>,
+        input: &[u8],
+        output: &mut W,
+    ) -> Result<(), W::Error> {
+        for &byte in input {
+            match byte {
+                b'\r' | b'\n' => {
+                    let mut output = Output::new(output);
+                    let result = if self.overflow {
+                        writeln!(output, "Line too long\r")
+                    } else {
+                        execute(scheduler, &self.line, &mut output)
+                    };
+                    self.line.clear();
+                    self.overflow = false;
+                    if result.is_err() {
+                        return output.into_result();
+                    }
+                }
+                byte => self.overflow |= self.line.push(byte).is_err(),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn execute<const TASK_COUNT: usize, const CORE_FREQ: u32>(
+    scheduler: &Scheduler<TASK_COUNT, CORE_FREQ>,
+    line: &[u8],
+    output: &mut impl fmt::Write,
+) -> fmt::Result {
+    let line = match core::str::from_utf8(line) {
+        Ok(line) => line.trim(),
+        Err(_) => return writeln!(output, "Invalid command\r"),
+    };
+    // Task names may contain spaces, the value is always the last argument
+    let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+    let args = args.trim();
+    match command {
+        "" => Ok(()),
+        "tasks" => {
+            for TaskInfo {
+                name,
+                period,
+                offset,
+                pending_events,
+                suspended,
+                active,
+                ..
+            } in scheduler.tasks()
+            {
+                let state = match (suspended, active) {
+                    (true, _) => "suspended",
+                    (false, false) => "inactive",
+                    (false, true) => "active",
+                };
+                writeln!(
+                    output,
+                    "{}: period {:?}, offset {:?}, events {:#010x}, {}\r",
+                    name, period, offset, pending_events, state
+                )?;
+            }
+            Ok(())
+        }
+        "stats" => {
+            for TaskInfo {
+                name,
+                run_count,
+                last_run,
+                next_deadline,
+                execution_time,
+                max_execution_time,
+                deadline_misses,
+                ..
+            } in scheduler.tasks()
+            {
+                writeln!(
+                    output,
+                    "{}: runs {}, last run {:?}, next deadline {:?}, execution time {} us \
+                     (max {} us), deadline misses {}\r",
+                    name,
+                    run_count,
+                    last_run,
+                    next_deadline,
+                    execution_time,
+                    max_execution_time,
+                    deadline_misses
+                )?;
+            }
+            writeln!(output, "busy time {} us\r", scheduler.busy_time())
+        }
+        "event" => match split_value(args).and_then(|(name, value)| Some((name, parse(value)?))) {
+            Some((name, event)) if scheduler.has_task(name) => {
+                scheduler.set_task_event(name, event as EventMask);
+                writeln!(output, "OK\r")
+            }
+            Some((name, _)) => writeln!(output, "Unknown task {}\r", name),
+            None => writeln!(output, "Usage: event <task> <mask>\r"),
+        },
+        "suspend" | "resume" if !scheduler.has_task(args) => {
+            writeln!(output, "Unknown task {}\r", args)
+        }
+        "suspend" => {
+            scheduler.suspend_task(args);
+            writeln!(output, "OK\r")
+        }
+        "resume" => {
+            scheduler.resume_task(args);
+            writeln!(output, "OK\r")
+        }
+        "period" => match split_value(args).and_then(|(name, value)| Some((name, parse(value)?))) {
+            Some((name, _)) if !scheduler.has_task(name) => {
+                writeln!(output, "Unknown task {}\r", name)
+            }
+            Some((name, period)) => match scheduler.set_task_period(name, period) {
+                Ok(()) => writeln!(output, "OK\r"),
+                Err(error) => writeln!(output, "Task {} period not changed, {}\r", name, error),
+            },
+            None => writeln!(output, "Usage: period <task> <ms>\r"),
+        },
+        "mode" if args.is_empty() => match scheduler.current_mode() {
+            Some(mode) => writeln!(output, "{}\r", mode),
+            None => writeln!(output, "No mode\r"),
+        },
+        "mode" => match scheduler.switch_mode(args) {
+            Ok(()) => writeln!(output, "OK\r"),
+            Err(_) => writeln!(output, "Unknown mode {}\r", args),
+        },
+        _ => writeln!(output, "Unknown command {}\r", command),
+    }
+}
+
+fn split_value(args: &str) -> Option<(&str, &str)> {
+    args.rsplit_once(' ')
+        .map(|(name, value)| (name.trim_end(), value))
+}
+
+fn parse(value: &str) -> Option<u32> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Formats the responses into the writer, keeping the write error to report it
+struct Output<'a, W: Write> {
+    writer: &'a mut W,
+    error: Option<W::Error>,
+}
+
+impl<'a, W: Write> Output<'a, W> {
+    fn new(writer: &'a mut W) -> Self {
+        Output {
+            writer,
+            error: None,
+        }
+    }
+
+    fn into_result(self) -> Result<(), W::Error> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> fmt::Write for Output<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Task;
+    use std::{string::String, vec::Vec as StdVec};
+
+    const DUMMY_CORE_FREQ: u32 = 100_000_000;
+
+    struct Buffer(StdVec<u8>);
+
+    impl embedded_io::ErrorType for Buffer {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.0.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    fn run<const N: usize>(scheduler: &Scheduler<N, DUMMY_CORE_FREQ>, input: &str) -> String {
+        let mut shell: Shell<32> = Shell::new();
+        let mut output = Buffer(StdVec::new());
+        shell
+            .process(scheduler, input.as_bytes(), &mut output)
+            .unwrap();
+        String::from_utf8(output.0).unwrap()
+    }
+
+    #[test]
+    fn shell_commands() {
+        const TASK_COUNT: usize = 2;
+        fn dummy_process_runnable(_event_mask: EventMask) {}
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new(
+            "Cyclic task",
+            None,
+            Some(dummy_process_runnable),
+            Some(10),
+            None,
+        ));
+        scheduler.add_task(Task::new("Idle task", None, None, None, None));
+
+        assert_eq!(run(&scheduler, "event Idle task 0x11\r\n"), "OK\r\n");
+        assert_eq!(scheduler.get_task_event("Idle task"), Some(0x11));
+        assert_eq!(run(&scheduler, "suspend Idle task\n"), "OK\r\n");
+        assert_eq!(run(&scheduler, "period Cyclic task 20\n"), "OK\r\n");
+        assert_eq!(
+            run(&scheduler, "tasks\n"),
+            "Cyclic task: period Some(20), offset None, events 0x00000000, active\r\n\
+             Idle task: period None, offset None, events 0x00000011, suspended\r\n"
+        );
+        assert_eq!(
+            run(&scheduler, "stats\n"),
+            "Cyclic task: runs 0, last run None, next deadline Some(0), execution time 0 us \
+             (max 0 us), deadline misses 0\r\n\
+             Idle task: runs 0, last run None, next deadline None, execution time 0 us \
+             (max 0 us), deadline misses 0\r\n\
+             busy time 0 us\r\n"
+        );
+    }
+
+    #[test]
+    fn shell_errors() {
+        const TASK_COUNT: usize = 1;
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new("Idle task", None, None, None, None));
+
+        assert_eq!(run(&scheduler, "reboot\n"), "Unknown command reboot\r\n");
+        assert_eq!(run(&scheduler, "mode\n"), "No mode\r\n");
+        assert_eq!(
+            run(&scheduler, "mode Service\n"),
+            "Unknown mode Service\r\n"
+        );
+        assert_eq!(
+            run(&scheduler, "resume Busy task\n"),
+            "Unknown task Busy task\r\n"
+        );
+        assert_eq!(
+            run(&scheduler, "event Busy task 0x1\n"),
+            "Unknown task Busy task\r\n"
+        );
+        assert_eq!(
+            run(&scheduler, "period Busy task 10\n"),
+            "Unknown task Busy task\r\n"
+        );
+        assert_eq!(
+            run(&scheduler, "event Idle task\n"),
+            "Usage: event <task> <mask>\r\n"
+        );
+        assert_eq!(
+            run(&scheduler, "period Idle task 10\n"),
+            "Task Idle task period not changed, task is not cyclic\r\n"
+        );
+        assert_eq!(
+            run(&scheduler, "suspend a task with a too long name\n"),
+            "Line too long\r\n"
+        );
+    }
+}
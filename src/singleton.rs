@@ -0,0 +1,214 @@
+//! Scheduler singleton which can be placed in a `static`, granting exclusive
+//! access while configuring and shared access once launched
+
+use crate::{
+    dispatch::DispatchPolicy,
+    events::{Event, EventSet},
+    port::{critical_section, is_thread_mode},
+    EventMask, Scheduler, SchedulerError,
+};
+use core::cell::{Cell, UnsafeCell};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Scheduler can be configured, as long as no shared access is ongoing
+    Configurable { readers: usize },
+    /// Scheduler is exclusively borrowed for configuration
+    Configuring,
+    /// Scheduler is launched, only shared access is granted from now on
+    Running,
+}
+
+pub struct StaticScheduler<const TASK_COUNT: usize, const CORE_FREQ: u32> {
+    scheduler: UnsafeCell<Scheduler<TASK_COUNT, CORE_FREQ>>,
+    state: Cell<State>,
+    /// Thread which launched the scheduler, the only one running the super loop on the host
+    #[cfg(any(test, feature = "std"))]
+    launcher: Cell<Option<std::thread::ThreadId>>,
+}
+
+// State transitions happen inside critical sections and the scheduler is only exclusively
+// borrowed while no shared reference exists, from whichever context configures it, hence the
+// `Send` bound. Shared references to the whole scheduler, whose runtime state is made of plain
+// cells written by the super loop, are confined to the context of the super loop: thread mode
+// on the target and the launching thread on the host, see `StaticScheduler::in_thread_mode`.
+// Any other context only reaches a `SchedulerHandle`, which touches the names and modes fixed
+// by the configuration, the atomic suspension flags and the state locked by `Shared`
+unsafe impl<const TASK_COUNT: usize, const CORE_FREQ: u32> Sync
+    for StaticScheduler<TASK_COUNT, CORE_FREQ>
+where
+    Scheduler<TASK_COUNT, CORE_FREQ>: Send,
+{
+}
+
+impl<const TASK_COUNT: usize, const CORE_FREQ: u32> Default
+    for StaticScheduler<TASK_COUNT, CORE_FREQ>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const TASK_COUNT: usize, const CORE_FREQ: u32> StaticScheduler<TASK_COUNT, CORE_FREQ> {
+    pub const fn new() -> StaticScheduler<TASK_COUNT, CORE_FREQ> {
+        StaticScheduler {
+            scheduler: UnsafeCell::new(Scheduler::new()),
+            state: Cell::new(State::Configurable { readers: 0 }),
+            #[cfg(any(test, feature = "std"))]
+            launcher: Cell::new(None),
+        }
+    }
+
+    /// Creates a scheduler singleton dispatching the tasks according to `dispatch_policy`
+    pub const fn with_dispatch_policy(
+        dispatch_policy: &'static dyn DispatchPolicy,
+    ) -> StaticScheduler<TASK_COUNT, CORE_FREQ> {
+        StaticScheduler {
+            scheduler: UnsafeCell::new(Scheduler::with_dispatch_policy(dispatch_policy)),
+            state: Cell::new(State::Configurable { readers: 0 }),
+            #[cfg(any(test, feature = "std"))]
+            launcher: Cell::new(None),
+        }
+    }
+
+    /// Grants exclusive access to the scheduler to add tasks and register runnables,
+    /// panics if the scheduler is already launched or being accessed
+    pub fn configure<R>(&self, f: impl FnOnce(&mut Scheduler<TASK_COUNT, CORE_FREQ>) -> R) -> R {
+        critical_section(|_| match self.state.get() {
+            State::Configurable { readers: 0 } => self.state.set(State::Configuring),
+            State::Running => panic!("Scheduler cannot be configured once launched"),
+            _ => panic!("Scheduler cannot be configured while being accessed"),
+        });
+        // Safety: state guarantees there is no other reference to the scheduler
+        let result = f(unsafe { &mut *self.scheduler.get() });
+        critical_section(|_| self.state.set(State::Configurable { readers: 0 }));
+        result
+    }
+
+    /// Grants shared access to the task events, suspension and mode requests from any
+    /// execution context, returns `None` if the scheduler is being configured, e.g. when
+    /// called from an interrupt. Hence an event set from an interrupt during configuration
+    /// is dropped, interrupts raising events are meant to be enabled once the scheduler is
+    /// configured
+    pub fn with<R>(
+        &self,
+        f: impl FnOnce(SchedulerHandle<'_, TASK_COUNT, CORE_FREQ>) -> R,
+    ) -> Option<R> {
+        self.read(|scheduler| f(SchedulerHandle { scheduler }))
+    }
+
+    /// Grants shared access to the whole scheduler from the context of the super loop, e.g.
+    /// to run the shell or change task periods from a task. Returns `None` if the scheduler
+    /// is being configured or when called from another context: an interrupt handler on the
+    /// target, a thread other than the launching one on the host. Before launch, the host
+    /// runs `f` within the critical section as threads run in parallel
+    pub fn in_thread_mode<R>(
+        &self,
+        f: impl FnOnce(&Scheduler<TASK_COUNT, CORE_FREQ>) -> R,
+    ) -> Option<R> {
+        if !is_thread_mode() {
+            return None;
+        }
+        #[cfg(not(any(test, feature = "std")))]
+        return self.read(f);
+        #[cfg(any(test, feature = "std"))]
+        match self.launcher.get() {
+            Some(launcher) if launcher != std::thread::current().id() => None,
+            Some(_) => self.read(f),
+            None => critical_section(|_| self.read(f)),
+        }
+    }
+
+    fn read<R>(&self, f: impl FnOnce(&Scheduler<TASK_COUNT, CORE_FREQ>) -> R) -> Option<R> {
+        let granted = critical_section(|_| match self.state.get() {
+            State::Configurable { readers } => {
+                self.state.set(State::Configurable {
+                    readers: readers + 1,
+                });
+                true
+            }
+            State::Configuring => false,
+            State::Running => true,
+        });
+        if !granted {
+            return None;
+        }
+        // Safety: state guarantees there is no exclusive reference to the scheduler
+        let result = f(unsafe { &*self.scheduler.get() });
+        critical_section(|_| {
+            if let State::Configurable { readers } = self.state.get() {
+                self.state.set(State::Configurable {
+                    readers: readers - 1,
+                });
+            }
+        });
+        Some(result)
+    }
+
+    /// Launches the scheduler, from then on it can no longer be configured
+    pub fn launch(&self) {
+        critical_section(|_| match self.state.get() {
+            State::Configurable { readers: 0 } => {
+                self.state.set(State::Running);
+                #[cfg(any(test, feature = "std"))]
+                self.launcher.set(Some(std::thread::current().id()));
+            }
+            State::Running => panic!("Scheduler already launched"),
+            _ => panic!("Scheduler cannot be launched while being accessed"),
+        });
+        // Safety: once running, the scheduler is never exclusively borrowed again
+        unsafe { &*self.scheduler.get() }.launch();
+    }
+}
+
+/// Shared access to a scheduler from any execution context, limited to the task events,
+/// the suspension of tasks and mode requests, which are atomic or locked
+#[derive(Clone, Copy)]
+pub struct SchedulerHandle<'a, const TASK_COUNT: usize, const CORE_FREQ: u32> {
+    scheduler: &'a Scheduler<TASK_COUNT, CORE_FREQ>,
+}
+
+impl<const TASK_COUNT: usize, const CORE_FREQ: u32> SchedulerHandle<'_, TASK_COUNT, CORE_FREQ> {
+    /// See [`Scheduler::switch_mode`]
+    #[inline]
+    pub fn switch_mode(&self, name: &str) -> Result<(), SchedulerError> {
+        self.scheduler.switch_mode(name)
+    }
+
+    #[inline]
+    pub fn suspend_task(&self, name: &str) {
+        self.scheduler.suspend_task(name)
+    }
+
+    #[inline]
+    pub fn resume_task(&self, name: &str) {
+        self.scheduler.resume_task(name)
+    }
+
+    #[inline]
+    pub fn set_task_event(&self, name: &str, event: EventMask) {
+        self.scheduler.set_task_event(name, event)
+    }
+
+    #[inline]
+    pub fn clear_task_event(&self, name: &str, event: EventMask) {
+        self.scheduler.clear_task_event(name, event)
+    }
+
+    /// See [`Scheduler::set_task_event_typed`]
+    #[inline]
+    pub fn set_task_event_typed<E: Event>(&self, name: &str, events: impl Into<EventSet<E>>) {
+        self.scheduler.set_task_event_typed(name, events)
+    }
+
+    /// See [`Scheduler::clear_task_event_typed`]
+    #[inline]
+    pub fn clear_task_event_typed<E: Event>(&self, name: &str, events: impl Into<EventSet<E>>) {
+        self.scheduler.clear_task_event_typed(name, events)
+    }
+
+    #[inline]
+    pub fn get_task_event(&self, name: &str) -> Option<u32> {
+        self.scheduler.get_task_event(name)
+    }
+}
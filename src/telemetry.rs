@@ -0,0 +1,194 @@
+//! Binary telemetry frames with the scheduler statistics, serialized with postcard
+//! and COBS framed, so frames are delimited by a zero byte within a byte stream
+
+use crate::{port, Scheduler, TaskInfo};
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+/// Version of the frame layout, it is the first byte of every frame and
+/// increased on any incompatible change
+pub const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskStats<'a> {
+    pub name: &'a str,
+    pub run_count: u32,
+    /// Duration of the last execution in microseconds
+    pub execution_time: u32,
+    /// Longest execution in microseconds
+    pub max_execution_time: u32,
+    pub deadline_misses: u32,
+}
+
+impl From<TaskInfo> for TaskStats<'static> {
+    fn from(info: TaskInfo) -> Self {
+        TaskStats {
+            name: info.name,
+            run_count: info.run_count,
+            execution_time: info.execution_time,
+            max_execution_time: info.max_execution_time,
+            deadline_misses: info.deadline_misses,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Frame<'a> {
+    version: u8,
+    timestamp: u32,
+    cpu_load: u16,
+    tasks: &'a [TaskStats<'a>],
+}
+
+/// Encoder of the telemetry frames, the CPU load of each frame is computed
+/// over the time elapsed since the previous one
+#[derive(Debug, Default)]
+pub struct Telemetry {
+    busy_time: u32,
+    timestamp: u32,
+}
+
+impl Telemetry {
+    pub const fn new() -> Telemetry {
+        Telemetry {
+            busy_time: 0,
+            timestamp: 0,
+        }
+    }
+
+    /// Encodes a frame into `buffer`, returns the encoded bytes including the zero delimiter
+    pub fn encode<'b, const TASK_COUNT: usize, const CORE_FREQ: u32>(
+        &mut self,
+        scheduler: &Scheduler<TASK_COUNT, CORE_FREQ>,
+        buffer: &'b mut [u8],
+    ) -> Result<&'b mut [u8], postcard::Error> {
+        let (timestamp, busy_time) = (port::now_us(), scheduler.busy_time());
+        let window = timestamp.wrapping_sub(self.timestamp);
+        let busy = busy_time.wrapping_sub(self.busy_time);
+        // CPU load in per mille
+        let cpu_load = match window {
+            0 => 0,
+            window => (busy as u64 * 1_000 / window as u64).min(1_000) as u16,
+        };
+        self.timestamp = timestamp;
+        self.busy_time = busy_time;
+
+        let tasks: Vec<TaskStats, TASK_COUNT> = scheduler.tasks().map(TaskStats::from).collect();
+        postcard::to_slice_cobs(
+            &Frame {
+                version: VERSION,
+                timestamp,
+                cpu_load,
+                tasks: &tasks,
+            },
+            buffer,
+        )
+    }
+}
+
+/// Frame decoded on the host, task names are borrowed from the frame bytes
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DecodedFrame<'a> {
+    pub version: u8,
+    /// Time of the frame in microseconds, wrapping on overflow
+    pub timestamp: u32,
+    /// CPU load in per mille since the previous frame
+    pub cpu_load: u16,
+    #[serde(borrow)]
+    pub tasks: std::vec::Vec<TaskStats<'a>>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum DecodeError {
+    /// Frame is empty or not properly COBS encoded
+    Framing,
+    /// Frame layout version is not supported
+    Version(u8),
+    Postcard(postcard::Error),
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Framing => write!(f, "invalid framing"),
+            DecodeError::Version(version) => write!(f, "unsupported frame version {}", version),
+            DecodeError::Postcard(error) => write!(f, "invalid frame, {}", error),
+        }
+    }
+}
+
+/// Decodes a frame in place, `frame` holds the bytes of a frame without the zero delimiter
+#[cfg(feature = "std")]
+pub fn decode(frame: &mut [u8]) -> Result<DecodedFrame<'_>, DecodeError> {
+    let length = cobs::decode_in_place(frame).map_err(|_| DecodeError::Framing)?;
+    let frame = &frame[..length];
+    match frame.first() {
+        Some(&VERSION) => postcard::from_bytes(frame).map_err(DecodeError::Postcard),
+        Some(&version) => Err(DecodeError::Version(version)),
+        None => Err(DecodeError::Framing),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventMask, Task};
+
+    const DUMMY_CORE_FREQ: u32 = 100_000_000;
+
+    #[test]
+    fn telemetry_frame_round_trip() {
+        const TASK_COUNT: usize = 2;
+        fn dummy_process_runnable(_event_mask: EventMask) {}
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.add_task(Task::new(
+            "Cyclic task",
+            None,
+            Some(dummy_process_runnable),
+            Some(10),
+            None,
+        ));
+        scheduler.add_task(Task::new("Idle task", None, None, None, None));
+        scheduler.init_tasks(0);
+        assert!(scheduler.process_tasks(10));
+
+        let mut buffer = [0u8; 64];
+        let frame = Telemetry::new().encode(&scheduler, &mut buffer).unwrap();
+        let (delimiter, frame) = frame.split_last_mut().unwrap();
+        assert_eq!(*delimiter, 0);
+        assert!(!frame.contains(&0));
+
+        let frame = decode(frame).unwrap();
+        assert_eq!(frame.version, VERSION);
+        assert_eq!(
+            frame.tasks,
+            [
+                TaskStats {
+                    name: "Cyclic task",
+                    run_count: 1,
+                    execution_time: 0,
+                    max_execution_time: 0,
+                    deadline_misses: 0,
+                },
+                TaskStats {
+                    name: "Idle task",
+                    run_count: 0,
+                    execution_time: 0,
+                    max_execution_time: 0,
+                    deadline_misses: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn telemetry_frame_version() {
+        // Version 2 frame, COBS encoded
+        let mut frame = [0x02, 0x02];
+        assert!(matches!(decode(&mut frame), Err(DecodeError::Version(2))));
+    }
+}
@@ -0,0 +1,154 @@
+//! Sink which writes the trace points in the Chrome Trace Event JSON format,
+//! traces can be opened with Perfetto (ui.perfetto.dev) or chrome://tracing.
+//! Records captured on target are converted with `tools/trace-converter`
+
+use super::{Cause, TraceEvent, TraceSink};
+use std::{
+    io::{self, Write},
+    sync::Mutex,
+    time::Instant,
+};
+
+/// Task executions are written as duration events on a single timeline, events
+/// set or cleared are written as instant events. The closing bracket of the
+/// JSON array is optional in this format, so the output is valid at any time
+pub struct ChromeTraceSink<W: Write + Send> {
+    inner: Mutex<Inner<W>>,
+}
+
+struct Inner<W> {
+    writer: W,
+    start: Instant,
+    first: bool,
+}
+
+impl<W: Write + Send> ChromeTraceSink<W> {
+    pub fn new(writer: W) -> ChromeTraceSink<W> {
+        ChromeTraceSink {
+            inner: Mutex::new(Inner {
+                writer,
+                start: Instant::now(),
+                first: true,
+            }),
+        }
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.lock().writer.flush()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner<W>> {
+        self.inner.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl<W: Write + Send> TraceSink for ChromeTraceSink<W> {
+    fn trace(&self, event: TraceEvent) {
+        let mut inner = self.lock();
+        let timestamp = inner.start.elapsed().as_micros();
+        let separator = if inner.first { "[\n" } else { ",\n" };
+        inner.first = false;
+        // Tracing must not disturb the scheduler, write errors are discarded
+        let _ = write!(inner.writer, "{}", separator)
+            .and_then(|_| write_event(&mut inner.writer, timestamp, &event));
+    }
+}
+
+/// Writes a trace point as a trace event object, `timestamp` in microseconds
+pub fn write_event(writer: &mut impl Write, timestamp: u128, event: &TraceEvent) -> io::Result<()> {
+    let (name, category, phase, args) = match *event {
+        TraceEvent::TaskDispatched { task, cause } => (
+            task,
+            "task",
+            "B",
+            match cause {
+                Cause::Cycle => String::from(r#"{"cause":"cycle"}"#),
+                Cause::Events(event_mask) => {
+                    format!(r#"{{"cause":"events","event_mask":{}}}"#, event_mask)
+                }
+            },
+        ),
+        TraceEvent::TaskFinished { task } => (task, "task", "E", String::from("{}")),
+        TraceEvent::EventSet { task, event } => {
+            (task, "event", "i", format!(r#"{{"set":{}}}"#, event))
+        }
+        TraceEvent::EventCleared { task, event } => {
+            (task, "event", "i", format!(r#"{{"cleared":{}}}"#, event))
+        }
+        TraceEvent::IdleEntered => ("idle", "idle", "B", String::from("{}")),
+        TraceEvent::IdleLeft => ("idle", "idle", "E", String::from("{}")),
+    };
+    write!(writer, r#"{{"name":""#)?;
+    write_escaped(writer, name)?;
+    write!(
+        writer,
+        r#"","cat":"{}","ph":"{}","ts":{},"pid":1,"tid":1,"#,
+        category, phase, timestamp
+    )?;
+    if phase == "i" {
+        write!(writer, r#""s":"t","#)?;
+    }
+    write!(writer, r#""args":{}}}"#, args)
+}
+
+fn write_escaped(writer: &mut impl Write, value: &str) -> io::Result<()> {
+    for c in value.chars() {
+        match c {
+            '"' => write!(writer, "\\\"")?,
+            '\\' => write!(writer, "\\\\")?,
+            c if c.is_control() => write!(writer, "\\u{:04x}", c as u32)?,
+            c => write!(writer, "{}", c)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_string(timestamp: u128, event: TraceEvent) -> String {
+        let mut output = Vec::new();
+        write_event(&mut output, timestamp, &event).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn trace_event_format() {
+        assert_eq!(
+            to_string(
+                10,
+                TraceEvent::TaskDispatched {
+                    task: "Dummy \"task\"",
+                    cause: Cause::Events(3)
+                }
+            ),
+            r#"{"name":"Dummy \"task\"","cat":"task","ph":"B","ts":10,"pid":1,"tid":1,"args":{"cause":"events","event_mask":3}}"#
+        );
+        assert_eq!(
+            to_string(20, TraceEvent::TaskFinished { task: "Dummy task" }),
+            r#"{"name":"Dummy task","cat":"task","ph":"E","ts":20,"pid":1,"tid":1,"args":{}}"#
+        );
+        assert_eq!(
+            to_string(
+                30,
+                TraceEvent::EventSet {
+                    task: "Dummy task",
+                    event: 1
+                }
+            ),
+            r#"{"name":"Dummy task","cat":"event","ph":"i","ts":30,"pid":1,"tid":1,"s":"t","args":{"set":1}}"#
+        );
+    }
+
+    #[test]
+    fn trace_sink_output() {
+        let sink = ChromeTraceSink::new(Vec::new());
+        sink.trace(TraceEvent::IdleEntered);
+        sink.trace(TraceEvent::IdleLeft);
+
+        let output = String::from_utf8(sink.lock().writer.clone()).unwrap();
+        assert!(output.starts_with("[\n{\"name\":\"idle\""));
+        assert_eq!(output.matches(",\n{").count(), 1);
+    }
+}
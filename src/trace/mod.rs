@@ -0,0 +1,194 @@
+//! Structured trace points of the scheduler activity, emitted through a
+//! registered [`TraceSink`] which routes them to e.g. RTT, ITM/SWO or RAM
+
+#[cfg(feature = "std")]
+mod chrome;
+mod record;
+
+#[cfg(feature = "std")]
+pub use chrome::{write_event, ChromeTraceSink};
+#[cfg(feature = "std")]
+pub use record::{decode, DecodeError};
+pub use record::{encode, MAX_RECORD_SIZE};
+
+use crate::{
+    port::{self, log},
+    resources::Shared,
+    EventMask, TaskName,
+};
+use heapless::Deque;
+
+/// Reason why a task is dispatched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Cause {
+    /// Execution cycle elapsed
+    Cycle,
+    /// Events pending on the task
+    Events(EventMask),
+}
+
+/// Trace points, events carry no timestamp since the scheduler tick is too
+/// coarse to measure task executions, sinks timestamp them upon reception
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TraceEvent {
+    TaskDispatched { task: TaskName, cause: Cause },
+    TaskFinished { task: TaskName },
+    EventSet { task: TaskName, event: EventMask },
+    EventCleared { task: TaskName, event: EventMask },
+    IdleEntered,
+    IdleLeft,
+}
+
+/// Destination of the trace points, it may be called from interrupts as events
+/// can be set or cleared from any execution context
+pub trait TraceSink: Sync {
+    fn trace(&self, event: TraceEvent);
+}
+
+/// Sink which prints every trace point through the port logger
+#[derive(Debug, Default)]
+pub struct LogSink;
+
+impl TraceSink for LogSink {
+    fn trace(&self, event: TraceEvent) {
+        log!("{:?}", event);
+    }
+}
+
+/// Sink which keeps the last `N` trace points in RAM along with their timestamp in
+/// microseconds, the oldest ones are overwritten
+pub struct TraceBuffer<const N: usize> {
+    events: Shared<Deque<(u32, TraceEvent), N>>,
+}
+
+impl<const N: usize> Default for TraceBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> TraceBuffer<N> {
+    pub const fn new() -> TraceBuffer<N> {
+        TraceBuffer {
+            events: Shared::new(Deque::new()),
+        }
+    }
+
+    /// Takes the buffered trace points, from oldest to newest
+    pub fn drain(&self, mut f: impl FnMut(TraceEvent)) {
+        while let Some((_, event)) = self.events.lock(|events| events.pop_front()) {
+            f(event);
+        }
+    }
+
+    /// Takes the buffered trace points as timestamped records, from oldest to newest,
+    /// to be written to a byte stream decoded on the host, see [`encode`]
+    pub fn drain_records(&self, mut f: impl FnMut(&[u8])) {
+        let mut buffer = [0; MAX_RECORD_SIZE];
+        while let Some((timestamp, event)) = self.events.lock(|events| events.pop_front()) {
+            f(encode(timestamp, &event, &mut buffer));
+        }
+    }
+}
+
+impl<const N: usize> TraceSink for TraceBuffer<N> {
+    fn trace(&self, event: TraceEvent) {
+        self.events.lock(|events| {
+            if events.is_full() {
+                events.pop_front();
+            }
+            // Room has been made above
+            let _ = events.push_back((port::now_us(), event));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Scheduler, Task};
+
+    const DUMMY_CORE_FREQ: u32 = 100_000_000;
+
+    #[test]
+    fn trace_buffer_overwrites_oldest() {
+        let buffer: TraceBuffer<2> = TraceBuffer::new();
+        buffer.trace(TraceEvent::IdleEntered);
+        buffer.trace(TraceEvent::IdleLeft);
+        buffer.trace(TraceEvent::IdleEntered);
+
+        let mut events = std::vec::Vec::new();
+        buffer.drain(|event| events.push(event));
+        assert_eq!(events, [TraceEvent::IdleLeft, TraceEvent::IdleEntered]);
+
+        buffer.drain(|_| panic!("Buffer expected to be empty"));
+    }
+
+    #[test]
+    fn trace_buffer_records() {
+        let buffer: TraceBuffer<2> = TraceBuffer::new();
+        buffer.trace(TraceEvent::IdleEntered);
+        port::advance_us(250);
+        buffer.trace(TraceEvent::IdleLeft);
+
+        let mut stream = std::vec::Vec::new();
+        buffer.drain_records(|record| stream.extend_from_slice(record));
+        let (entered, _, size) =
+            decode(&stream, |name| panic!("Unexpected name {}", name)).unwrap();
+        let (left, event, _) = decode(&stream[size..], |_| "").unwrap();
+        assert_eq!(left - entered, 250);
+        assert_eq!(event, TraceEvent::IdleLeft);
+    }
+
+    #[test]
+    fn scheduler_trace_points() {
+        const TASK_COUNT: usize = 1;
+        const TASK_NAME: &str = "Dummy task";
+        const TASK_EVENT: EventMask = 0x00000003;
+        static BUFFER: TraceBuffer<8> = TraceBuffer::new();
+        fn dummy_process_runnable(_event_mask: EventMask) {}
+
+        let mut scheduler: Scheduler<TASK_COUNT, DUMMY_CORE_FREQ> = Scheduler::new();
+        scheduler.register_trace_sink(&BUFFER);
+        scheduler.add_task(Task::new(
+            TASK_NAME,
+            None,
+            Some(dummy_process_runnable),
+            Some(10),
+            None,
+        ));
+        scheduler.init_tasks(0);
+
+        scheduler.set_task_event(TASK_NAME, TASK_EVENT);
+        scheduler.clear_task_event(TASK_NAME, 0x00000001);
+        assert!(scheduler.process_tasks(10));
+
+        let mut events = std::vec::Vec::new();
+        BUFFER.drain(|event| events.push(event));
+        assert_eq!(
+            events,
+            [
+                TraceEvent::EventSet {
+                    task: TASK_NAME,
+                    event: TASK_EVENT
+                },
+                TraceEvent::EventCleared {
+                    task: TASK_NAME,
+                    event: 0x00000001
+                },
+                TraceEvent::TaskDispatched {
+                    task: TASK_NAME,
+                    cause: Cause::Events(0x00000002)
+                },
+                TraceEvent::TaskFinished { task: TASK_NAME },
+                TraceEvent::TaskDispatched {
+                    task: TASK_NAME,
+                    cause: Cause::Cycle
+                },
+                TraceEvent::TaskFinished { task: TASK_NAME },
+            ]
+        );
+    }
+}
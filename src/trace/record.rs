@@ -0,0 +1,206 @@
+//! Binary records of the trace points along with their timestamp, written by the target
+//! to a byte stream, e.g. an RTT channel or a dump of a [`TraceBuffer`](super::TraceBuffer),
+//! and decoded on the host to be converted into a Chrome trace by `tools/trace-converter`.
+//! Each record starts with the length of the rest of the record, followed by the kind of
+//! trace point, the timestamp in microseconds, the event mask if any and the task name,
+//! integers being little endian
+
+use super::{Cause, TraceEvent};
+use crate::EventMask;
+
+/// Size of the longest record, task names are truncated to fit in it
+pub const MAX_RECORD_SIZE: usize = 256;
+
+const DISPATCHED_CYCLE: u8 = 0;
+const DISPATCHED_EVENTS: u8 = 1;
+const FINISHED: u8 = 2;
+const EVENT_SET: u8 = 3;
+const EVENT_CLEARED: u8 = 4;
+const IDLE_ENTERED: u8 = 5;
+const IDLE_LEFT: u8 = 6;
+
+/// Encodes a trace point with its timestamp in microseconds into `buffer`, returns the
+/// bytes of the record
+pub fn encode<'b>(
+    timestamp: u32,
+    event: &TraceEvent,
+    buffer: &'b mut [u8; MAX_RECORD_SIZE],
+) -> &'b [u8] {
+    let (kind, event_mask, name): (u8, Option<EventMask>, &str) = match *event {
+        TraceEvent::TaskDispatched {
+            task,
+            cause: Cause::Cycle,
+        } => (DISPATCHED_CYCLE, None, task),
+        TraceEvent::TaskDispatched {
+            task,
+            cause: Cause::Events(event_mask),
+        } => (DISPATCHED_EVENTS, Some(event_mask), task),
+        TraceEvent::TaskFinished { task } => (FINISHED, None, task),
+        TraceEvent::EventSet { task, event } => (EVENT_SET, Some(event), task),
+        TraceEvent::EventCleared { task, event } => (EVENT_CLEARED, Some(event), task),
+        TraceEvent::IdleEntered => (IDLE_ENTERED, None, ""),
+        TraceEvent::IdleLeft => (IDLE_LEFT, None, ""),
+    };
+    buffer[1] = kind;
+    buffer[2..6].copy_from_slice(&timestamp.to_le_bytes());
+    let mut length = 6;
+    if let Some(event_mask) = event_mask {
+        buffer[length..length + 4].copy_from_slice(&event_mask.to_le_bytes());
+        length += 4;
+    }
+    let mut name_length = name.len().min(MAX_RECORD_SIZE - length);
+    while !name.is_char_boundary(name_length) {
+        name_length -= 1;
+    }
+    buffer[length..length + name_length].copy_from_slice(&name.as_bytes()[..name_length]);
+    length += name_length;
+    buffer[0] = (length - 1) as u8;
+    &buffer[..length]
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Record shorter than its length or than the fields of its kind
+    Truncated,
+    /// Kind of trace point not supported
+    Kind(u8),
+    /// Task name is not valid UTF-8
+    Name,
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "truncated record"),
+            DecodeError::Kind(kind) => write!(f, "unsupported trace point kind {}", kind),
+            DecodeError::Name => write!(f, "task name is not valid UTF-8"),
+        }
+    }
+}
+
+/// Decodes the record at the beginning of `stream`, returns the timestamp, the trace point
+/// and the size of the record. Trace points refer to static task names, so each name
+/// decoded is resolved by `name`, e.g. by interning it
+#[cfg(feature = "std")]
+pub fn decode<'a>(
+    stream: &'a [u8],
+    mut name: impl FnMut(&'a str) -> crate::TaskName,
+) -> Result<(u32, TraceEvent, usize), DecodeError> {
+    let size = 1 + *stream.first().ok_or(DecodeError::Truncated)? as usize;
+    let record = stream.get(1..size).ok_or(DecodeError::Truncated)?;
+    let field = |range: core::ops::Range<usize>| -> Result<u32, DecodeError> {
+        let bytes = record.get(range).ok_or(DecodeError::Truncated)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let kind = *record.first().ok_or(DecodeError::Truncated)?;
+    let timestamp = field(1..5)?;
+    let mut task = |start: usize| -> Result<crate::TaskName, DecodeError> {
+        let bytes = record.get(start..).ok_or(DecodeError::Truncated)?;
+        Ok(name(
+            core::str::from_utf8(bytes).map_err(|_| DecodeError::Name)?,
+        ))
+    };
+    let event = match kind {
+        DISPATCHED_CYCLE => TraceEvent::TaskDispatched {
+            task: task(5)?,
+            cause: Cause::Cycle,
+        },
+        DISPATCHED_EVENTS => TraceEvent::TaskDispatched {
+            cause: Cause::Events(field(5..9)?),
+            task: task(9)?,
+        },
+        FINISHED => TraceEvent::TaskFinished { task: task(5)? },
+        EVENT_SET => TraceEvent::EventSet {
+            event: field(5..9)?,
+            task: task(9)?,
+        },
+        EVENT_CLEARED => TraceEvent::EventCleared {
+            event: field(5..9)?,
+            task: task(9)?,
+        },
+        IDLE_ENTERED => TraceEvent::IdleEntered,
+        IDLE_LEFT => TraceEvent::IdleLeft,
+        kind => return Err(DecodeError::Kind(kind)),
+    };
+    Ok((timestamp, event, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_round_trip() {
+        let events = [
+            TraceEvent::TaskDispatched {
+                task: "Dummy task",
+                cause: Cause::Cycle,
+            },
+            TraceEvent::TaskDispatched {
+                task: "Dummy task",
+                cause: Cause::Events(3),
+            },
+            TraceEvent::TaskFinished { task: "Dummy task" },
+            TraceEvent::EventSet {
+                task: "Dummy task",
+                event: 1,
+            },
+            TraceEvent::EventCleared {
+                task: "Dummy task",
+                event: 2,
+            },
+            TraceEvent::IdleEntered,
+            TraceEvent::IdleLeft,
+        ];
+        let mut stream = std::vec::Vec::new();
+        let mut buffer = [0; MAX_RECORD_SIZE];
+        for (timestamp, event) in events.iter().enumerate() {
+            stream.extend_from_slice(encode(timestamp as u32 * 100, event, &mut buffer));
+        }
+
+        let mut position = 0;
+        for (timestamp, event) in events.iter().enumerate() {
+            let (decoded_timestamp, decoded_event, size) = decode(&stream[position..], |name| {
+                assert_eq!(name, "Dummy task");
+                "Dummy task"
+            })
+            .unwrap();
+            assert_eq!(decoded_timestamp, timestamp as u32 * 100);
+            assert_eq!(decoded_event, *event);
+            position += size;
+        }
+        assert_eq!(position, stream.len());
+    }
+
+    #[test]
+    fn record_errors() {
+        // Name truncated on a character boundary to fit in a record
+        let name: &'static str = "é".repeat(200).leak();
+        let mut buffer = [0; MAX_RECORD_SIZE];
+        let record = encode(0, &TraceEvent::TaskFinished { task: name }, &mut buffer).to_vec();
+        assert_eq!(record.len(), MAX_RECORD_SIZE);
+        let (_, event, _) = decode(&record, |name| {
+            assert_eq!(name.chars().count(), 125);
+            "Dummy task"
+        })
+        .unwrap();
+        assert_eq!(event, TraceEvent::TaskFinished { task: "Dummy task" });
+
+        assert_eq!(
+            decode(&record[..10], |_| "").unwrap_err(),
+            DecodeError::Truncated
+        );
+        // Record of zero length, without kind
+        assert_eq!(decode(&[0], |_| "").unwrap_err(), DecodeError::Truncated);
+        assert_eq!(
+            decode(&[5, 9, 0, 0, 0, 0], |_| "").unwrap_err(),
+            DecodeError::Kind(9)
+        );
+        assert_eq!(
+            decode(&[5, EVENT_SET, 0, 0, 0, 0], |_| "").unwrap_err(),
+            DecodeError::Truncated
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Offline schedulability analysis of a task set
+//! Reads the cyclic tasks, from a file or the standard input, one per line as
+//! `name,period_ms,offset_ms,wcet_us` in the order of the task list, and prints the
+//! worst-case response time and jitter of each task. Exits with an error status if
+//! any task can miss its deadline
+
+use non_preemptive_scheduler::analysis::{self, TaskParameters};
+use std::{
+    env, fs,
+    io::{self, Read},
+    process,
+};
+
+const HEADER: [&str; 7] = [
+    "task",
+    "period_ms",
+    "wcet_us",
+    "worst_response_time_us",
+    "worst_blocking_us",
+    "jitter_us",
+    "deadline_misses",
+];
+
+fn parse_line(line: &str) -> Option<TaskParameters<'_>> {
+    let mut fields = line.split(',').map(str::trim);
+    let task = TaskParameters {
+        name: fields.next()?,
+        period: fields.next()?.parse().ok()?,
+        offset: fields.next()?.parse().ok()?,
+        wcet: fields.next()?.parse().ok()?,
+    };
+    fields.next().is_none().then_some(task)
+}
+
+fn main() {
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("Usage: schedulability [task set file, standard input if none]");
+                println!("Each line describes a task as name,period_ms,offset_ms,wcet_us");
+                return;
+            }
+            _ => path = Some(arg),
+        }
+    }
+
+    let result = match &path {
+        Some(path) => fs::read_to_string(path),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map(|_| input)
+        }
+    };
+    let input = result.unwrap_or_else(|error| {
+        eprintln!("Task set cannot be read, {}", error);
+        process::exit(2);
+    });
+
+    let mut tasks = Vec::new();
+    // Empty lines and lines starting with `#` are skipped
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_line(line) {
+            Some(task) => tasks.push(task),
+            None => {
+                eprintln!("Line {} is not name,period_ms,offset_ms,wcet_us", index + 1);
+                process::exit(2);
+            }
+        }
+    }
+
+    let analysis = analysis::analyse(&tasks).unwrap_or_else(|error| {
+        eprintln!("Task set cannot be analysed, {}", error);
+        process::exit(2);
+    });
+
+    let header = HEADER.map(String::from);
+    let rows: Vec<[String; 7]> = tasks
+        .iter()
+        .zip(analysis.tasks.iter())
+        .map(|(task, result)| {
+            [
+                task.name.to_string(),
+                task.period.to_string(),
+                task.wcet.to_string(),
+                result.worst_response_time.to_string(),
+                result.worst_blocking.to_string(),
+                result.jitter.to_string(),
+                result.deadline_misses.to_string(),
+            ]
+        })
+        .collect();
+    let mut widths = header.each_ref().map(String::len);
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+
+    println!("hyperperiod {} ms", analysis.hyperperiod);
+    if !analysis.is_schedulable() {
+        for task in analysis.tasks.iter().filter(|task| !task.is_schedulable()) {
+            println!("Task {} can miss its deadline", task.name);
+        }
+        process::exit(1);
+    }
+}
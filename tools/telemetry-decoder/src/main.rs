@@ -0,0 +1,120 @@
+//! Host decoder of the scheduler telemetry frames
+//! Parses a captured byte stream, from a file or the standard input, and prints
+//! the statistics of each frame as CSV or, with `--table`, as an aligned table
+
+use non_preemptive_scheduler::telemetry::{self, DecodedFrame};
+use std::{
+    borrow::Cow,
+    env, fs,
+    io::{self, Read},
+    process,
+};
+
+const HEADER: [&str; 7] = [
+    "timestamp_us",
+    "cpu_load_permille",
+    "task",
+    "run_count",
+    "execution_time_us",
+    "max_execution_time_us",
+    "deadline_misses",
+];
+
+fn rows<'a>(frame: &'a DecodedFrame<'a>) -> impl Iterator<Item = [String; 7]> + 'a {
+    frame.tasks.iter().map(|task| {
+        [
+            frame.timestamp.to_string(),
+            frame.cpu_load.to_string(),
+            task.name.to_string(),
+            task.run_count.to_string(),
+            task.execution_time.to_string(),
+            task.max_execution_time.to_string(),
+            task.deadline_misses.to_string(),
+        ]
+    })
+}
+
+/// Quotes a CSV field containing a separator, a quote or a line break, quotes being
+/// doubled, so that any task name is read back as a single field
+fn csv_field(cell: &str) -> Cow<'_, str> {
+    if cell.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", cell.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
+
+fn main() {
+    let (mut table, mut path) = (false, None);
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--table" => table = true,
+            "-h" | "--help" => {
+                println!(
+                    "Usage: telemetry-decoder [--table] [capture file, standard input if none]"
+                );
+                return;
+            }
+            _ => path = Some(arg),
+        }
+    }
+
+    let mut stream = Vec::new();
+    let result = match &path {
+        Some(path) => fs::read(path).map(|bytes| stream = bytes),
+        None => io::stdin().read_to_end(&mut stream).map(|_| ()),
+    };
+    if let Err(error) = result {
+        eprintln!("Capture cannot be read, {}", error);
+        process::exit(1);
+    }
+
+    let mut output: Vec<[String; 7]> = Vec::new();
+    // Frames are delimited by a zero byte, a capture may start or end within a frame
+    for (index, frame) in stream
+        .split_mut(|byte| *byte == 0)
+        .filter(|frame| !frame.is_empty())
+        .enumerate()
+    {
+        match telemetry::decode(frame) {
+            Ok(frame) => output.extend(rows(&frame)),
+            Err(error) => eprintln!("Frame {} skipped, {}", index, error),
+        }
+    }
+
+    let header = HEADER.map(String::from);
+    if table {
+        let mut widths = header.each_ref().map(String::len);
+        for row in output.iter() {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        for row in std::iter::once(&header).chain(output.iter()) {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            println!("{}", cells.join("  ").trim_end());
+        }
+    } else {
+        for row in std::iter::once(&header).chain(output.iter()) {
+            let cells: Vec<Cow<str>> = row.iter().map(|cell| csv_field(cell)).collect();
+            println!("{}", cells.join(","));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field("usb_process"), "usb_process");
+        assert_eq!(csv_field("usb, led"), "\"usb, led\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
@@ -0,0 +1,149 @@
+//! Host converter of the scheduler trace records into a Chrome trace
+//! Reads a capture of the records written by the target, e.g. the records of a drained
+//! `TraceBuffer` or an RTT channel, from a file or the standard input, and writes the
+//! trace in the Chrome Trace Event JSON format, to be opened with Perfetto
+//! (ui.perfetto.dev) or chrome://tracing
+
+use non_preemptive_scheduler::{
+    trace::{self, DecodeError},
+    TaskName,
+};
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, Read, Write},
+    process,
+};
+
+/// Converts the records of the capture, returns the offset and the error of the record
+/// which stopped the conversion if any, as records cannot be delimited past it.
+/// Timestamps wrapping on overflow are unwrapped, assuming consecutive records are less
+/// than 71 minutes apart
+fn convert(capture: &[u8], writer: &mut impl Write) -> io::Result<Option<(usize, DecodeError)>> {
+    // Task names are interned, trace points referring to static names
+    let mut names: HashMap<&str, TaskName> = HashMap::new();
+    let (mut position, mut previous, mut wraps) = (0, None, 0u128);
+    let mut result = None;
+    write!(writer, "[")?;
+    while position < capture.len() {
+        let (timestamp, event, size) = match trace::decode(&capture[position..], |name| {
+            *names
+                .entry(name)
+                .or_insert_with(|| String::from(name).leak())
+        }) {
+            Ok(record) => record,
+            Err(error) => {
+                result = Some((position, error));
+                break;
+            }
+        };
+        if previous.is_some_and(|previous| timestamp < previous) {
+            wraps += 1;
+        }
+        let separator = if previous.is_none() { "\n" } else { ",\n" };
+        previous = Some(timestamp);
+        write!(writer, "{}", separator)?;
+        trace::write_event(writer, (wraps << 32) + timestamp as u128, &event)?;
+        position += size;
+    }
+    writeln!(writer, "\n]")?;
+    Ok(result)
+}
+
+fn main() {
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("Usage: trace-converter [capture file, standard input if none]");
+                println!("The Chrome trace is written to the standard output");
+                return;
+            }
+            _ => path = Some(arg),
+        }
+    }
+
+    let mut capture = Vec::new();
+    let result = match &path {
+        Some(path) => fs::read(path).map(|bytes| capture = bytes),
+        None => io::stdin().read_to_end(&mut capture).map(|_| ()),
+    };
+    if let Err(error) = result {
+        eprintln!("Capture cannot be read, {}", error);
+        process::exit(2);
+    }
+
+    let mut output = io::BufWriter::new(io::stdout().lock());
+    match convert(&capture, &mut output).and_then(|result| output.flush().map(|_| result)) {
+        Ok(None) => (),
+        // A capture may end within a record, whereas a complete record lacking fields is an error
+        Ok(Some((position, DecodeError::Truncated)))
+            if position + 1 + capture[position] as usize > capture.len() =>
+        {
+            eprintln!("Capture ends within a record, which is skipped")
+        }
+        Ok(Some((position, error))) => {
+            eprintln!(
+                "Conversion stopped at byte {}, {}, the trace holds the records before it",
+                position, error
+            );
+            process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("Trace cannot be written, {}", error);
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use non_preemptive_scheduler::trace::{Cause, TraceEvent, MAX_RECORD_SIZE};
+
+    fn capture(records: &[(u32, TraceEvent)]) -> Vec<u8> {
+        let mut buffer = [0; MAX_RECORD_SIZE];
+        records
+            .iter()
+            .flat_map(|(timestamp, event)| trace::encode(*timestamp, event, &mut buffer).to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn capture_conversion() {
+        let capture = capture(&[
+            (
+                u32::MAX - 9,
+                TraceEvent::TaskDispatched {
+                    task: "Dummy task",
+                    cause: Cause::Cycle,
+                },
+            ),
+            (5, TraceEvent::TaskFinished { task: "Dummy task" }),
+        ]);
+        let mut output = Vec::new();
+        assert!(convert(&capture, &mut output).unwrap().is_none());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                "[\n",
+                r#"{"name":"Dummy task","cat":"task","ph":"B","ts":4294967286,"pid":1,"tid":1,"args":{"cause":"cycle"}}"#,
+                ",\n",
+                r#"{"name":"Dummy task","cat":"task","ph":"E","ts":4294967301,"pid":1,"tid":1,"args":{}}"#,
+                "\n]\n"
+            )
+        );
+    }
+
+    #[test]
+    fn truncated_capture() {
+        let capture = capture(&[
+            (0, TraceEvent::IdleEntered),
+            (10, TraceEvent::TaskFinished { task: "Dummy task" }),
+        ]);
+        let mut output = Vec::new();
+        let result = convert(&capture[..capture.len() - 1], &mut output).unwrap();
+        assert_eq!(result, Some((6, DecodeError::Truncated)));
+        assert_eq!(String::from_utf8(output).unwrap().matches("\"ph\"").count(), 1);
+    }
+}